@@ -1,7 +1,6 @@
 //! Configuration related to the _Docker_ execution backend.
 
 use bon::Builder;
-use bon::builder;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -5,7 +5,6 @@ use std::collections::HashMap;
 use std::sync::LazyLock;
 
 use bon::Builder;
-use bon::builder;
 use regex::Captures;
 use regex::Regex;
 use serde::Deserialize;
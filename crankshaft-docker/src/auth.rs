@@ -0,0 +1,386 @@
+//! Registry authentication.
+
+use std::collections::HashMap;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use bollard::auth::DockerCredentials;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::Error;
+use crate::Result;
+
+/// The server address Docker uses for credentials to Docker Hub.
+const DOCKER_HUB_ADDRESS: &str = "https://index.docker.io/v1/";
+
+/// The hosts that refer to Docker Hub in image references.
+const DOCKER_HUB_HOSTS: &[&str] = &["docker.io", "index.docker.io", "registry-1.docker.io"];
+
+/// The username a credential helper returns when its secret is an identity
+/// token.
+const IDENTITY_TOKEN_USERNAME: &str = "<token>";
+
+/// Credentials for a container registry.
+///
+/// Credentials are passed to operations that communicate with a registry,
+/// such as [`Docker::ensure_image_with_auth()`](crate::Docker::ensure_image_with_auth)
+/// and [`Docker::push_image()`](crate::Docker::push_image).
+#[derive(Clone, PartialEq, Eq)]
+pub enum RegistryAuth {
+    /// A username and password.
+    Password {
+        /// The address of the registry (e.g., `ghcr.io`).
+        server: String,
+        /// The username.
+        username: String,
+        /// The password.
+        password: String,
+    },
+    /// An identity token (e.g., as issued by `docker login` for some
+    /// registries).
+    IdentityToken {
+        /// The address of the registry (e.g., `ghcr.io`).
+        server: String,
+        /// The identity token.
+        token: String,
+    },
+}
+
+impl RegistryAuth {
+    /// Creates credentials from a username and password.
+    pub fn password(
+        server: impl Into<String>,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        Self::Password {
+            server: server.into(),
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    /// Creates credentials from an identity token.
+    pub fn identity_token(server: impl Into<String>, token: impl Into<String>) -> Self {
+        Self::IdentityToken {
+            server: server.into(),
+            token: token.into(),
+        }
+    }
+
+    /// Gets the address of the registry the credentials are for.
+    pub fn server(&self) -> &str {
+        match self {
+            Self::Password { server, .. } | Self::IdentityToken { server, .. } => server,
+        }
+    }
+
+    /// Reads the credentials for the registry of an image from the user's
+    /// Docker configuration.
+    ///
+    /// The configuration is read from `$DOCKER_CONFIG/config.json` if
+    /// `DOCKER_CONFIG` is set and `~/.docker/config.json` otherwise. If the
+    /// configuration names a credential helper for the registry (via
+    /// `credHelpers` or `credsStore`), the helper is run to retrieve the
+    /// credentials; otherwise, the credentials stored in `auths` are used.
+    ///
+    /// Returns `Ok(None)` if there is no configuration or it has no
+    /// credentials for the registry.
+    pub fn from_docker_config(image: impl AsRef<str>) -> Result<Option<Self>> {
+        let Some(path) = config_path() else {
+            return Ok(None);
+        };
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = std::fs::read_to_string(&path).map_err(|e| {
+            Error::Message(format!(
+                "failed to read Docker configuration `{path}`: {e}",
+                path = path.display()
+            ))
+        })?;
+
+        DockerConfig::parse(&contents, &path)?.credentials(registry(image.as_ref()))
+    }
+}
+
+impl std::fmt::Debug for RegistryAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Password {
+                server, username, ..
+            } => f
+                .debug_struct("Password")
+                .field("server", server)
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+            Self::IdentityToken { server, .. } => f
+                .debug_struct("IdentityToken")
+                .field("server", server)
+                .field("token", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+impl From<RegistryAuth> for DockerCredentials {
+    fn from(auth: RegistryAuth) -> Self {
+        match auth {
+            RegistryAuth::Password {
+                server,
+                username,
+                password,
+            } => DockerCredentials {
+                username: Some(username),
+                password: Some(password),
+                serveraddress: Some(server),
+                ..Default::default()
+            },
+            RegistryAuth::IdentityToken { server, token } => DockerCredentials {
+                identitytoken: Some(token),
+                serveraddress: Some(server),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Gets the registry for an image reference.
+///
+/// Images without a registry host are from Docker Hub, as are images with one
+/// of Docker Hub's hosts (e.g., `docker.io/library/ubuntu`), for which the
+/// address that credentials are stored under for Docker Hub is returned.
+pub(crate) fn registry(image: &str) -> &str {
+    match image.split_once('/') {
+        Some((host, _)) if DOCKER_HUB_HOSTS.contains(&host) => DOCKER_HUB_ADDRESS,
+        Some((host, _)) if host.contains('.') || host.contains(':') || host == "localhost" => host,
+        _ => DOCKER_HUB_ADDRESS,
+    }
+}
+
+/// Gets the path to the user's Docker configuration.
+fn config_path() -> Option<PathBuf> {
+    match std::env::var_os("DOCKER_CONFIG") {
+        Some(dir) => Some(PathBuf::from(dir).join("config.json")),
+        None => dirs::home_dir().map(|home| home.join(".docker").join("config.json")),
+    }
+}
+
+/// The parts of a Docker configuration file that hold credentials.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DockerConfig {
+    /// The stored credentials, keyed by registry.
+    #[serde(default)]
+    auths: HashMap<String, AuthEntry>,
+
+    /// The default credential helper.
+    creds_store: Option<String>,
+
+    /// The credential helpers, keyed by registry.
+    #[serde(default)]
+    cred_helpers: HashMap<String, String>,
+}
+
+/// A stored credential within a Docker configuration file.
+#[derive(Debug, Default, Deserialize)]
+struct AuthEntry {
+    /// The base64-encoded `username:password`.
+    auth: Option<String>,
+
+    /// An identity token.
+    identitytoken: Option<String>,
+}
+
+/// The credentials returned by a credential helper.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HelperCredentials {
+    /// The username, or `<token>` for an identity token.
+    username: String,
+
+    /// The password or identity token.
+    secret: String,
+}
+
+impl DockerConfig {
+    /// Parses a Docker configuration file.
+    fn parse(contents: &str, path: &Path) -> Result<Self> {
+        serde_json::from_str(contents).map_err(|e| {
+            Error::Message(format!(
+                "invalid Docker configuration `{path}`: {e}",
+                path = path.display()
+            ))
+        })
+    }
+
+    /// Gets the credentials for a registry.
+    fn credentials(&self, registry: &str) -> Result<Option<RegistryAuth>> {
+        if let Some(helper) = self
+            .cred_helpers
+            .get(registry)
+            .or(self.creds_store.as_ref())
+        {
+            return run_helper(helper, registry);
+        }
+
+        // Entries may be keyed by a bare host or by a URL
+        let Some(entry) = self.auths.iter().find_map(|(key, entry)| {
+            let host = key
+                .trim_start_matches("https://")
+                .trim_start_matches("http://")
+                .split('/')
+                .next()
+                .unwrap_or_default();
+            (key == registry || host == registry).then_some(entry)
+        }) else {
+            return Ok(None);
+        };
+
+        if let Some(token) = entry.identitytoken.as_ref().filter(|t| !t.is_empty()) {
+            return Ok(Some(RegistryAuth::identity_token(registry, token)));
+        }
+
+        let Some(auth) = &entry.auth else {
+            return Ok(None);
+        };
+
+        let decoded = STANDARD
+            .decode(auth)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| {
+                Error::Message(format!(
+                    "invalid stored credentials for registry `{registry}`"
+                ))
+            })?;
+
+        match decoded.split_once(':') {
+            Some((username, password)) => {
+                Ok(Some(RegistryAuth::password(registry, username, password)))
+            }
+            None => Err(Error::Message(format!(
+                "invalid stored credentials for registry `{registry}`"
+            ))),
+        }
+    }
+}
+
+/// Runs a credential helper to get the credentials for a registry.
+fn run_helper(helper: &str, registry: &str) -> Result<Option<RegistryAuth>> {
+    let program = format!("docker-credential-{helper}");
+    debug!("getting credentials for registry `{registry}` from `{program}`");
+
+    let mut child = Command::new(&program)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Message(format!("failed to run credential helper `{program}`: {e}")))?;
+
+    // SAFETY: standard input was set to be piped above.
+    let mut stdin = child.stdin.take().unwrap();
+    stdin
+        .write_all(registry.as_bytes())
+        .map_err(|e| Error::Message(format!("failed to run credential helper `{program}`: {e}")))?;
+    drop(stdin);
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| Error::Message(format!("failed to run credential helper `{program}`: {e}")))?;
+
+    if !output.status.success() {
+        // Helpers report missing credentials as a failure
+        debug!(
+            "credential helper `{program}` has no credentials for registry `{registry}`: {stderr}",
+            stderr = String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return Ok(None);
+    }
+
+    let credentials: HelperCredentials = serde_json::from_slice(&output.stdout).map_err(|e| {
+        Error::Message(format!(
+            "invalid output from credential helper `{program}`: {e}"
+        ))
+    })?;
+
+    if credentials.username == IDENTITY_TOKEN_USERNAME {
+        Ok(Some(RegistryAuth::identity_token(
+            registry,
+            credentials.secret,
+        )))
+    } else {
+        Ok(Some(RegistryAuth::password(
+            registry,
+            credentials.username,
+            credentials.secret,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registries() {
+        assert_eq!(registry("ubuntu"), DOCKER_HUB_ADDRESS);
+        assert_eq!(registry("library/ubuntu:22.04"), DOCKER_HUB_ADDRESS);
+        assert_eq!(registry("docker.io/library/ubuntu"), DOCKER_HUB_ADDRESS);
+        assert_eq!(
+            registry("index.docker.io/library/ubuntu"),
+            DOCKER_HUB_ADDRESS
+        );
+        assert_eq!(
+            registry("registry-1.docker.io/org/image:1"),
+            DOCKER_HUB_ADDRESS
+        );
+        assert_eq!(registry("ghcr.io/org/image:1"), "ghcr.io");
+        assert_eq!(registry("localhost:5000/image"), "localhost:5000");
+        assert_eq!(registry("localhost/image"), "localhost");
+    }
+
+    #[test]
+    fn stored_credentials() {
+        let config = DockerConfig::parse(
+            r#"{
+                "auths": {
+                    "https://index.docker.io/v1/": { "auth": "dXNlcjpwYXNzOndvcmQ=" },
+                    "https://ghcr.io": { "identitytoken": "token" }
+                }
+            }"#,
+            Path::new("config.json"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.credentials(DOCKER_HUB_ADDRESS).unwrap(),
+            Some(RegistryAuth::password(
+                DOCKER_HUB_ADDRESS,
+                "user",
+                "pass:word"
+            ))
+        );
+        assert_eq!(
+            config.credentials("ghcr.io").unwrap(),
+            Some(RegistryAuth::identity_token("ghcr.io", "token"))
+        );
+        assert_eq!(config.credentials("quay.io").unwrap(), None);
+    }
+
+    #[test]
+    fn redacted_debug() {
+        let auth = RegistryAuth::password("ghcr.io", "user", "secret");
+        assert!(!format!("{auth:?}").contains("secret"));
+    }
+}
@@ -0,0 +1,63 @@
+//! Blocking wrappers for use from synchronous code.
+//!
+//! Each method blocks the current thread on the given Tokio runtime
+//! [`Handle`] until the corresponding asynchronous method completes.
+//!
+//! These methods must not be called from within an asynchronous context (for
+//! example, from within a task spawned on a Tokio runtime): blocking on a
+//! runtime from within one of its tasks panics.
+
+use std::process::Output;
+
+use tokio::runtime::Handle;
+
+use crate::Container;
+use crate::Docker;
+use crate::Result;
+use crate::container::Builder;
+
+impl Docker {
+    /// Blocks on [`Self::ensure_image()`].
+    pub fn ensure_image_blocking(&self, handle: &Handle, image: impl AsRef<str>) -> Result<()> {
+        handle.block_on(self.ensure_image(image))
+    }
+
+    /// Blocks on [`Self::run_once()`].
+    pub fn run_once_blocking(
+        &self,
+        handle: &Handle,
+        image: impl AsRef<str>,
+        cmd: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<String> {
+        handle.block_on(self.run_once(image, cmd))
+    }
+}
+
+impl Builder {
+    /// Blocks on [`Self::try_build()`].
+    pub fn try_build_blocking(self, handle: &Handle, name: impl AsRef<str>) -> Result<Container> {
+        handle.block_on(self.try_build(name))
+    }
+}
+
+impl Container {
+    /// Blocks on [`Self::start()`].
+    pub fn start_blocking(&self, handle: &Handle) -> Result<()> {
+        handle.block_on(self.start())
+    }
+
+    /// Blocks on [`Self::run()`].
+    pub fn run_blocking(&self, handle: &Handle, started: impl FnOnce()) -> Result<Output> {
+        handle.block_on(self.run(started))
+    }
+
+    /// Blocks on [`Self::remove()`].
+    pub fn remove_blocking(&self, handle: &Handle) -> Result<()> {
+        handle.block_on(self.remove())
+    }
+
+    /// Blocks on [`Self::force_remove()`].
+    pub fn force_remove_blocking(&self, handle: &Handle) -> Result<()> {
+        handle.block_on(self.force_remove())
+    }
+}
@@ -1,34 +1,104 @@
 //! Containers.
 
+use std::collections::HashMap;
 use std::io::Cursor;
+use std::net::IpAddr;
 #[cfg(unix)]
 use std::os::unix::process::ExitStatusExt as _;
 #[cfg(windows)]
 use std::os::windows::process::ExitStatusExt as _;
+use std::path::Path;
+use std::pin::pin;
 use std::process::ExitStatus;
 use std::process::Output;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
 
 use bollard::Docker;
 use bollard::body_full;
 use bollard::container::LogOutput;
+use bollard::exec::CreateExecOptions;
+use bollard::exec::StartExecOptions;
+use bollard::exec::StartExecResults;
 use bollard::query_parameters::AttachContainerOptions;
+use bollard::query_parameters::DownloadFromContainerOptions;
 use bollard::query_parameters::InspectContainerOptions;
+use bollard::query_parameters::KillContainerOptions;
+use bollard::query_parameters::LogsOptions;
 use bollard::query_parameters::RemoveContainerOptions;
+use bollard::query_parameters::RestartContainerOptions;
 use bollard::query_parameters::StartContainerOptions;
+use bollard::query_parameters::StatsOptions;
+use bollard::query_parameters::StopContainerOptions;
 use bollard::query_parameters::UploadToContainerOptions;
 use bollard::query_parameters::WaitContainerOptions;
+use bollard::secret::ContainerInspectResponse;
 use bollard::secret::ContainerWaitResponse;
+use bollard::secret::MountPoint;
+use bollard::secret::NetworkConnectRequest;
+use bollard::secret::NetworkDisconnectRequest;
+use futures::Stream;
 use futures::TryStreamExt as _;
+use hyper::Method;
+use rand::Rng as _;
+use sha2::Digest as _;
+use sha2::Sha256;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt as _;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt as _;
 use tokio_stream::StreamExt as _;
+use tokio_util::io::StreamReader;
 use tracing::debug;
 use tracing::trace;
+use tracing::warn;
 
 use crate::Error;
 use crate::Result;
+use crate::raw::DaemonAddress;
 
 mod builder;
+mod exec;
+mod find;
+mod guard;
+mod job;
+mod observer;
+mod pipeline;
+mod reap;
+mod run;
+mod spec;
+mod times;
+mod tmpfs;
 
 pub use builder::Builder;
+pub use builder::SeLinuxRelabel;
+use builder::endpoint_settings;
+pub use exec::ExecHandle;
+pub use exec::ExecInspect;
+pub(crate) use find::find_containers;
+pub use guard::ContainerGuard;
+pub use job::JobResult;
+pub use job::JobSpec;
+pub(crate) use job::run_job;
+pub use observer::LifecycleObserver;
+pub use pipeline::Stage;
+pub(crate) use pipeline::run_pipeline;
+pub use reap::ReapReport;
+pub(crate) use reap::reap_by_label;
+use run::AbortOnDrop;
+use run::Heartbeat;
+pub use run::RunOptions;
+pub use run::RunOutput;
+pub use run::RunStats;
+use run::StatsSampler;
+pub use spec::ContainerSpec;
+pub use spec::MountSpec;
+pub use spec::PortProtocol;
+pub use spec::PortSpec;
+pub use times::ContainerTimes;
+pub use tmpfs::TmpfsOptions;
 
 /// The default capacity of bytes for a TAR being built.
 ///
@@ -37,174 +107,1799 @@ pub use builder::Builder;
 /// allocations.
 const DEFAULT_TAR_CAPACITY: usize = 0xFFFF;
 
+/// Builds a TAR archive from files given as a path, contents, and mode.
+///
+/// See [`entry_path()`] for how paths are handled.
+///
+/// The archive is deterministic: entries are sorted by path and their headers
+/// only hold the path, size, and mode, with the modification time and owner
+/// zeroed, so the same files always produce an identical archive. The sort is
+/// stable, so the last of any duplicate paths is still extracted last.
+pub(crate) fn build_archive<'a>(
+    files: impl IntoIterator<Item = (&'a str, &'a [u8], u32)>,
+) -> Result<Vec<u8>> {
+    let mut files = files
+        .into_iter()
+        .map(|(path, contents, mode)| Ok((entry_path(path)?, contents, mode)))
+        .collect::<Result<Vec<_>>>()?;
+    files.sort_by_key(|(path, ..)| *path);
+
+    let mut tar = tar::Builder::new(Vec::with_capacity(DEFAULT_TAR_CAPACITY));
+
+    for (path, contents, mode) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(contents.len() as u64);
+        header.set_mode(mode);
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+
+        // SAFETY: the path has been validated and writing to a `Vec` cannot fail,
+        // so this will always unwrap.
+        tar.append_data(&mut header, path, Cursor::new(contents))
+            .unwrap();
+    }
+
+    // SAFETY: writing to a `Vec` cannot fail, so this will always unwrap.
+    Ok(tar.into_inner().unwrap())
+}
+
+/// Computes a stable hash of files to be uploaded to a container, given as a
+/// path, contents, and mode as with [`Container::upload_files()`].
+///
+/// The hash is the hexadecimal SHA-256 digest of the TAR archive that is
+/// uploaded for the files, which is deterministic: it only depends on the
+/// paths, contents, and modes of the files and not on the order they are
+/// given in. It can therefore be used as a cache key to detect whether the
+/// inputs of a run match those of a previous one. Paths are normalized as when
+/// uploading (see [`Container::upload_file()`]), so `/in.txt` and `in.txt`
+/// hash the same; an error is returned if any path is invalid.
+pub fn content_hash(files: &[(String, Vec<u8>, u32)]) -> Result<String> {
+    let archive = build_archive(
+        files
+            .iter()
+            .map(|(path, contents, mode)| (path.as_str(), contents.as_slice(), *mode)),
+    )?;
+    Ok(format!("{:x}", Sha256::digest(archive)))
+}
+
+/// Checks the total size of the files within a directory on the host against
+/// a limit, if there is one.
+fn check_dir_size(src: &Path, max_bytes: Option<u64>) -> Result<()> {
+    let Some(limit) = max_bytes else {
+        return Ok(());
+    };
+
+    let size = dir_size(src).map_err(|e| archive_error(src, e))?;
+    if size > limit {
+        return Err(Error::UploadTooLarge { size, limit });
+    }
+
+    Ok(())
+}
+
+/// Creates the error for a directory on the host that could not be archived.
+fn archive_error(src: &Path, e: std::io::Error) -> Error {
+    Error::Message(format!(
+        "failed to archive directory `{src}`: {e}",
+        src = src.display()
+    ))
+}
+
+/// Builds a TAR archive of the contents of a directory on the host.
+///
+/// If `max_bytes` is set, the total size of the files within the directory is
+/// checked against it before the archive is built. Symbolic links are archived
+/// as links rather than followed.
+#[cfg(not(feature = "async-tar"))]
+fn build_dir_archive(src: &Path, max_bytes: Option<u64>) -> Result<Vec<u8>> {
+    check_dir_size(src, max_bytes)?;
+
+    let mut tar = tar::Builder::new(Vec::with_capacity(DEFAULT_TAR_CAPACITY));
+    tar.follow_symlinks(false);
+    tar.append_dir_all(".", src)
+        .map_err(|e| archive_error(src, e))?;
+    tar.into_inner().map_err(|e| archive_error(src, e))
+}
+
+/// The capacity of the buffer between the task writing a streamed TAR archive
+/// and the request uploading it.
+#[cfg(feature = "async-tar")]
+const TAR_STREAM_CAPACITY: usize = 0xFFFF;
+
+/// Streams a TAR archive of the contents of a directory on the host.
+///
+/// The archive is written by a separate task as the stream is consumed, so at
+/// most [`TAR_STREAM_CAPACITY`] bytes of it are held in memory at once. If the
+/// archive cannot be written, the stream ends with the error. Symbolic links
+/// are archived as links rather than followed.
+#[cfg(feature = "async-tar")]
+fn stream_dir_archive(
+    src: std::path::PathBuf,
+) -> impl Stream<Item = std::io::Result<bytes::Bytes>> + Send + 'static {
+    let (writer, reader) = tokio::io::duplex(TAR_STREAM_CAPACITY);
+    let task = tokio::spawn(async move {
+        let mut tar = async_tar::Builder::new(writer);
+        tar.follow_symlinks(false);
+        let result = tar.append_dir_all(".", &src).await;
+
+        // The builder panics if it is dropped without being finished, so it must
+        // be finished even if the directory could not be archived
+        let finished = tar.into_inner().await.map(drop);
+
+        result
+            .and(finished)
+            .map_err(|e| std::io::Error::new(e.kind(), archive_error(&src, e).to_string()))
+    });
+
+    tokio_util::io::ReaderStream::new(reader).chain(
+        futures::stream::once(async move {
+            match task.await {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(Err(e)),
+                Err(e) => Some(Err(std::io::Error::other(e))),
+            }
+        })
+        .filter_map(|result| result),
+    )
+}
+
+/// Extracts the contents of the file archived by the Docker daemon when
+/// downloading a path from a container.
+///
+/// The archive of a file holds a single entry, which must be a regular file.
+fn extract_file(archive: &[u8], path: &str) -> Result<Vec<u8>> {
+    let error = |e: std::io::Error| {
+        Error::Message(format!(
+            "failed to read the archive of `{path}` downloaded from the container: {e}"
+        ))
+    };
+
+    let mut archive = tar::Archive::new(archive);
+    let mut entry = match archive.entries().map_err(error)?.next() {
+        Some(entry) => entry.map_err(error)?,
+        None => {
+            return Err(Error::Message(format!(
+                "the archive of `{path}` downloaded from the container is empty"
+            )));
+        }
+    };
+
+    if !entry.header().entry_type().is_file() {
+        return Err(Error::Message(format!(
+            "`{path}` in the container is not a regular file"
+        )));
+    }
+
+    let mut contents = Vec::with_capacity(entry.size().try_into().unwrap_or_default());
+    std::io::Read::read_to_end(&mut entry, &mut contents).map_err(error)?;
+    Ok(contents)
+}
+
+/// Extracts the file archived by the Docker daemon when downloading a path
+/// from a container into `writer`, reading the archive as it is received.
+///
+/// Unlike [`extract_file()`], the contents are never held in memory. Any
+/// extended headers (such as for long names) before the entry of the file are
+/// skipped.
+async fn extract_file_to(
+    mut archive: impl AsyncRead + Unpin,
+    path: &str,
+    mut writer: impl AsyncWrite + Unpin,
+) -> std::io::Result<()> {
+    let mut block = [0; HEADER_LEN];
+
+    loop {
+        archive.read_exact(&mut block).await?;
+        if block.iter().all(|b| *b == 0) {
+            return Err(std::io::Error::other(format!(
+                "the archive of `{path}` downloaded from the container is empty"
+            )));
+        }
+
+        let header = tar::Header::from_byte_slice(&block);
+        let entry_type = header.entry_type();
+        let size = header.entry_size()?;
+
+        if entry_type.is_file() {
+            let copied = tokio::io::copy(&mut (&mut archive).take(size), &mut writer).await?;
+            if copied < size {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+
+            return writer.flush().await;
+        }
+
+        if !(entry_type.is_pax_local_extensions()
+            || entry_type.is_pax_global_extensions()
+            || entry_type.is_gnu_longname()
+            || entry_type.is_gnu_longlink())
+        {
+            return Err(std::io::Error::other(format!(
+                "`{path}` in the container is not a regular file"
+            )));
+        }
+
+        // The data of an entry is padded to a whole number of blocks
+        let padded = size.div_ceil(HEADER_LEN as u64) * HEADER_LEN as u64;
+        tokio::io::copy(&mut (&mut archive).take(padded), &mut tokio::io::sink()).await?;
+    }
+}
+
+/// Converts an error from downloading a path from a container, restoring
+/// errors from the Docker daemon that were wrapped to be read.
+fn download_error(path: &str, e: std::io::Error) -> Error {
+    if e.get_ref()
+        .is_some_and(|inner| inner.is::<bollard::errors::Error>())
+    {
+        // SAFETY: the inner error was checked to be a bollard error above.
+        return Error::from(
+            *e.into_inner()
+                .unwrap()
+                .downcast::<bollard::errors::Error>()
+                .unwrap(),
+        );
+    }
+
+    Error::Message(format!(
+        "failed to download `{path}` from the container: {e}"
+    ))
+}
+
+/// Verifies the SHA-256 digest of the contents of a file.
+fn verify_sha256(path: &str, contents: &[u8], expected: &str) -> Result<()> {
+    let actual = format!("{:x}", Sha256::digest(contents));
+    if actual.eq_ignore_ascii_case(expected) {
+        return Ok(());
+    }
+
+    Err(Error::ChecksumMismatch {
+        path: path.to_string(),
+        expected: expected.to_ascii_lowercase(),
+        actual,
+    })
+}
+
+/// The length of the header of a TAR archive entry.
+const HEADER_LEN: usize = 512;
+
+/// Checks whether the header of a TAR archive entry is for an executable file
+/// or a symbolic link.
+fn is_executable_entry(header: &[u8]) -> bool {
+    let header = tar::Header::from_byte_slice(header);
+    let entry_type = header.entry_type();
+
+    if entry_type.is_symlink() {
+        return true;
+    }
+
+    (entry_type.is_file() || entry_type.is_hard_link())
+        && header.mode().is_ok_and(|mode| mode & 0o111 != 0)
+}
+
+/// Gets the total size in bytes of the files within a directory.
+///
+/// Only file metadata is read; symbolic links are not followed.
+fn dir_size(dir: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        if metadata.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else {
+            size += metadata.len();
+        }
+    }
+
+    Ok(size)
+}
+
+/// The length of the random suffix of a name from [`sanitize_name()`].
+const NAME_SUFFIX_LEN: usize = 12;
+
+/// The maximum length of the prefix of a name from [`sanitize_name()`].
+const MAX_NAME_PREFIX_LEN: usize = 64;
+
+/// Creates a unique, valid container name from a prefix.
+///
+/// Docker requires container names to match `[a-zA-Z0-9][a-zA-Z0-9_.-]+`, so
+/// characters of the prefix that are not allowed are replaced with `-`, any
+/// leading characters that cannot start a name are removed, and the prefix is
+/// truncated to 64 characters. A random suffix of 12 lowercase alphanumeric
+/// characters is then appended (e.g., `my-job-1-x3k9q0v7a2mz` for `my job/1`),
+/// making a conflict with another container's name vanishingly unlikely.
+///
+/// If nothing of the prefix remains, the name is only the random suffix.
+pub fn sanitize_name(prefix: &str) -> String {
+    let prefix = prefix
+        .trim_start_matches(|c: char| !c.is_ascii_alphanumeric())
+        .chars()
+        .take(MAX_NAME_PREFIX_LEN)
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                c
+            } else {
+                '-'
+            }
+        });
+
+    let mut name: String = prefix.collect();
+    if !name.is_empty() && !name.ends_with(['-', '_', '.']) {
+        name.push('-');
+    }
+
+    name.push_str(&random_suffix());
+    name
+}
+
+/// Checks whether a name is valid for Docker, which requires container and
+/// checkpoint names to match `[a-zA-Z0-9][a-zA-Z0-9_.-]+`.
+fn is_valid_name(name: &str) -> bool {
+    name.len() > 1
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+/// Generates a random suffix of lowercase alphanumeric characters.
+fn random_suffix() -> String {
+    rand::rng()
+        .sample_iter(&rand::distr::Alphanumeric)
+        .take(NAME_SUFFIX_LEN)
+        .map(|b| char::from(b).to_ascii_lowercase())
+        .collect()
+}
+
+/// Converts the exit code of a container or exec instance to an exit status.
+fn exit_status(exit_code: i64) -> ExitStatus {
+    // See WEXITSTATUS from wait(2) to explain the shift
+    #[cfg(unix)]
+    return ExitStatus::from_raw((exit_code as i32) << 8);
+
+    #[cfg(windows)]
+    return ExitStatus::from_raw(exit_code as u32);
+}
+
+/// Checks that the output of a container is from a successful exit.
+pub(crate) fn check_exit(output: &Output) -> Result<()> {
+    if output.status.success() {
+        return Ok(());
+    }
+
+    Err(Error::NonZeroExit {
+        // Statuses of containers are always created from an exit code
+        code: output.status.code().unwrap_or(-1),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    })
+}
+
+/// Samples the statistics of a container until the task is aborted.
+async fn sample_stats(
+    client: Docker,
+    name: String,
+    mut sampler: StatsSampler,
+    run_stats: Arc<Mutex<RunStats>>,
+) {
+    let mut interval = tokio::time::interval(sampler.interval);
+
+    loop {
+        interval.tick().await;
+
+        let mut stream = client.stats(
+            &name,
+            Some(StatsOptions {
+                stream: false,
+                one_shot: true,
+            }),
+        );
+
+        match stream.next().await {
+            Some(Ok(stats)) => {
+                // SAFETY: the lock is never held across a panic.
+                run_stats.lock().unwrap().record(&stats);
+                (sampler.callback)(&stats);
+            }
+            Some(Err(e)) => trace!("failed to sample statistics of container `{name}`: {e}"),
+            None => {}
+        }
+    }
+}
+
+/// Creates an error for failing to write the output of a container to a writer
+/// set with [`RunOptions::tee()`].
+fn tee_error(name: &str, e: std::io::Error) -> Error {
+    Error::Message(format!("failed to write output of container `{name}`: {e}"))
+}
+
+/// Sends heartbeats until the task is aborted.
+async fn send_heartbeats(mut heartbeat: Heartbeat) {
+    let start = tokio::time::Instant::now();
+    let mut interval = tokio::time::interval_at(start + heartbeat.interval, heartbeat.interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        (heartbeat.callback)(start.elapsed());
+    }
+}
+
+/// Gets the path of a TAR archive entry for a file being uploaded.
+///
+/// Leading slashes are trimmed, as entries must be relative to the directory
+/// the archive is extracted into.
+fn entry_path(path: &str) -> Result<&str> {
+    let trimmed = path.trim_start_matches('/');
+
+    if trimmed.is_empty() {
+        return Err(Error::Message(format!(
+            "upload path `{path}` does not name a file"
+        )));
+    }
+
+    if trimmed.split('/').any(|component| component == "..") {
+        return Err(Error::Message(format!(
+            "upload path `{path}` cannot contain `..`"
+        )));
+    }
+
+    Ok(trimmed)
+}
+
+/// Gets the absolute path of a hidden temporary file in the same directory as
+/// a file being uploaded (e.g., `/etc/.app.conf.x3k9q0v7a2mz.tmp` for
+/// `/etc/app.conf`).
+fn temp_sibling(path: &str, suffix: &str) -> Result<String> {
+    let path = entry_path(path)?;
+    let (dir, name) = match path.rsplit_once('/') {
+        Some((dir, name)) => (format!("/{dir}/"), name),
+        None => (String::from("/"), path),
+    };
+
+    if name.is_empty() {
+        return Err(Error::Message(format!(
+            "upload path `{path}` does not name a file"
+        )));
+    }
+
+    Ok(format!("{dir}.{name}.{suffix}.tmp"))
+}
+
 /// A container.
+///
+/// A container holds a clone of the [`Docker`] client it was created with,
+/// which shares that client's connection to the Docker daemon; see
+/// [`Docker`](crate::Docker) for more information.
 pub struct Container {
     /// A reference to the [`Docker`] client that will be used to create this
     /// container.
     client: Docker,
 
-    /// The name of the container.
-    name: String,
+    /// The name of the container.
+    name: String,
+
+    /// Whether or not standard output is attached.
+    attach_stdout: bool,
+
+    /// Whether or not standard output is attached.
+    attach_stderr: bool,
+
+    /// The observer of the container's lifecycle events.
+    observer: Option<Arc<dyn LifecycleObserver>>,
+
+    /// The address of the Docker daemon, if it is known.
+    address: Option<DaemonAddress>,
+}
+
+impl Container {
+    /// Creates a new [`Container`] if you already know the name of a container.
+    ///
+    /// You should typically use [`Self::builder()`] unless you receive the
+    /// container name externally from a user (say, on the command line as an
+    /// argument).
+    pub fn new(client: Docker, name: String, attach_stdout: bool, attach_stderr: bool) -> Self {
+        Self {
+            client,
+            name,
+            attach_stdout,
+            attach_stderr,
+            observer: None,
+            address: None,
+        }
+    }
+
+    /// Sets the address of the Docker daemon, which is required to checkpoint
+    /// and restore the container.
+    pub(crate) fn with_daemon_address(mut self, address: Option<DaemonAddress>) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// Sets the observer of the container's lifecycle events.
+    ///
+    /// See [`LifecycleObserver`] for more information.
+    pub fn with_observer(mut self, observer: Arc<dyn LifecycleObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Notifies the observer of the container, if there is one, of an event.
+    fn observe(&self, event: impl FnOnce(&dyn LifecycleObserver, &str, Instant)) {
+        if let Some(observer) = &self.observer {
+            event(observer.as_ref(), &self.name, Instant::now());
+        }
+    }
+
+    /// Uploads an input file to the container.
+    ///
+    /// This is equivalent to calling [`Self::upload_file_to()`] with a
+    /// destination directory of `/`, so `path` is always resolved against the
+    /// root directory of the container: both `/etc/app.conf` and
+    /// `etc/app.conf` are uploaded to `/etc/app.conf`.
+    ///
+    /// The file is sent as a TAR archive built in memory, even with the
+    /// `async-tar` feature enabled; only [`Self::upload_dir()`] streams its
+    /// archive.
+    pub async fn upload_file(&self, path: &str, contents: &[u8]) -> Result<()> {
+        self.upload_file_to("/", path, contents).await
+    }
+
+    /// Uploads an input file to the container, replacing any existing file
+    /// at `path` atomically.
+    ///
+    /// The file is first uploaded with [`Self::upload_file()`] to a hidden
+    /// temporary file in the same directory as `path` and is then renamed to
+    /// `path` by running `mv -f` within the container. As the rename happens
+    /// within a single filesystem, processes in the container observe either
+    /// the previous file or the complete new one, never a partially written
+    /// file. If the rename fails, an attempt is made to remove the temporary
+    /// file.
+    ///
+    /// The container must be running and must have an `mv` executable on its
+    /// `PATH` (no shell is required); use [`Self::upload_file()`] for images
+    /// without one, such as those built `FROM scratch`.
+    pub async fn upload_file_atomic(&self, path: &str, contents: &[u8]) -> Result<()> {
+        let dest = format!("/{path}", path = entry_path(path)?);
+        let temp = temp_sibling(path, &random_suffix())?;
+        self.upload_file(&temp, contents).await?;
+
+        debug!(
+            "renaming `{temp}` to `{dest}` in container `{container}`",
+            container = self.name
+        );
+
+        let renamed = self
+            .exec_output(["mv", "-f", &temp, &dest])
+            .await
+            .and_then(|output| check_exit(&output));
+
+        if let Err(e) = renamed {
+            if let Err(e) = self
+                .exec_output(["rm", "-f", &temp])
+                .await
+                .and_then(|output| check_exit(&output))
+            {
+                warn!(
+                    "failed to remove `{temp}` from container `{container}`: {e}",
+                    container = self.name
+                );
+            }
+
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Uploads an input file to a directory within the container.
+    ///
+    /// The file is sent as a TAR archive that the Docker daemon extracts into
+    /// `dest_dir`, which must already exist within the container. As entries
+    /// within a TAR archive must be relative, all leading slashes are trimmed
+    /// from `name` and the remainder is resolved against `dest_dir`: uploading
+    /// `app.conf` or `/app.conf` to `/etc` both result in `/etc/app.conf`.
+    ///
+    /// Keeping the leading slashes would not change where the file is placed,
+    /// as the Docker daemon always resolves archive entries relative to the
+    /// directory the archive is extracted into.
+    ///
+    /// An error is returned if `name` is empty once trimmed or if it contains
+    /// a `..` component.
+    pub async fn upload_file_to(&self, dest_dir: &str, name: &str, contents: &[u8]) -> Result<()> {
+        let archive = build_archive([(name, contents, 0o644)])?;
+
+        debug!(
+            "uploading `{name}` to `{dest_dir}` in container `{container}`",
+            container = self.name
+        );
+
+        self.upload_archive(dest_dir, archive).await
+    }
+
+    /// Uploads the contents of a directory on the host to a directory within
+    /// the container.
+    ///
+    /// The directory is sent as a TAR archive that the Docker daemon extracts
+    /// into `dest_dir`, which must already exist within the container.
+    ///
+    /// If `max_bytes` is set, the total size of the files within the directory
+    /// is computed from their metadata before the archive is built, and
+    /// [`Error::UploadTooLarge`] is returned if it exceeds the limit.
+    ///
+    /// By default, the whole archive is built in memory on a blocking thread
+    /// before it is uploaded. With the `async-tar` feature enabled, the archive
+    /// is instead built asynchronously and streamed to the Docker daemon as it
+    /// is written, so the memory used does not grow with the size of the
+    /// directory.
+    pub async fn upload_dir(
+        &self,
+        src: impl AsRef<Path>,
+        dest_dir: &str,
+        max_bytes: Option<u64>,
+    ) -> Result<()> {
+        let src = src.as_ref().to_path_buf();
+
+        debug!(
+            "uploading directory `{src}` to `{dest_dir}` in container `{container}`",
+            src = src.display(),
+            container = self.name
+        );
+
+        #[cfg(not(feature = "async-tar"))]
+        {
+            let archive = tokio::task::spawn_blocking(move || build_dir_archive(&src, max_bytes))
+                .await
+                .map_err(|e| Error::Message(format!("failed to archive directory: {e}")))??;
+
+            self.upload_archive(dest_dir, archive).await
+        }
+
+        #[cfg(feature = "async-tar")]
+        {
+            let dir = src.clone();
+            tokio::task::spawn_blocking(move || check_dir_size(&dir, max_bytes))
+                .await
+                .map_err(|e| Error::Message(format!("failed to archive directory: {e}")))??;
+
+            self.client
+                .upload_to_container(
+                    &self.name,
+                    Some(UploadToContainerOptions {
+                        path: dest_dir.to_string(),
+                        ..Default::default()
+                    }),
+                    bollard::body_try_stream(stream_dir_archive(src)),
+                )
+                .await
+                .map_err(Error::from)
+        }
+    }
+
+    /// Uploads multiple files, given as a path, contents, and mode, to the
+    /// container in a single archive.
+    ///
+    /// Paths are resolved against the root directory of the container as with
+    /// [`Self::upload_file()`]. If any path is invalid, an error is returned
+    /// and no files are uploaded.
+    ///
+    /// As with [`Self::upload_file()`], the archive is built in memory even
+    /// with the `async-tar` feature enabled.
+    pub async fn upload_files(&self, files: &[(String, Vec<u8>, u32)]) -> Result<()> {
+        let archive = build_archive(
+            files
+                .iter()
+                .map(|(path, contents, mode)| (path.as_str(), contents.as_slice(), *mode)),
+        )?;
+
+        debug!(
+            "uploading {count} files to container `{container}`",
+            count = files.len(),
+            container = self.name
+        );
+
+        self.upload_archive("/", archive).await
+    }
+
+    /// Uploads a TAR archive to be extracted into a directory within the
+    /// container.
+    pub(crate) async fn upload_archive(&self, dest_dir: &str, archive: Vec<u8>) -> Result<()> {
+        self.client
+            .upload_to_container(
+                &self.name,
+                Some(UploadToContainerOptions {
+                    path: dest_dir.to_string(),
+                    ..Default::default()
+                }),
+                body_full(archive.into()),
+            )
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Downloads the contents of a file from the container.
+    ///
+    /// The Docker daemon sends the file as a TAR archive, which is read into
+    /// memory and extracted. An error is returned if `path` does not exist or
+    /// is not a regular file (symbolic links are not followed). The container
+    /// does not need to be running.
+    pub async fn download_file(&self, path: &str) -> Result<Vec<u8>> {
+        debug!(
+            "downloading `{path}` from container `{container}`",
+            container = self.name
+        );
+
+        let archive = self
+            .client
+            .download_from_container(
+                &self.name,
+                Some(DownloadFromContainerOptions {
+                    path: path.to_string(),
+                }),
+            )
+            .try_fold(Vec::new(), |mut archive, bytes| async move {
+                archive.extend_from_slice(&bytes);
+                Ok(archive)
+            })
+            .await
+            .map_err(Error::from)?;
+
+        extract_file(&archive, path)
+    }
+
+    /// Downloads a file from the container to a path on the host.
+    ///
+    /// Unlike [`Self::download_file()`], the archive sent by the Docker daemon
+    /// is extracted as it is received, so large files are written to disk
+    /// without being held in memory. The file is written to exactly
+    /// `host_path`, which is created or truncated; if the download fails, the
+    /// partially written file is removed. As with [`Self::download_file()`],
+    /// `container_path` must be a regular file.
+    pub async fn download_to(&self, container_path: &str, host_path: &Path) -> Result<()> {
+        debug!(
+            "downloading `{container_path}` from container `{container}` to `{host_path}`",
+            container = self.name,
+            host_path = host_path.display()
+        );
+
+        let archive = StreamReader::new(
+            self.client
+                .download_from_container(
+                    &self.name,
+                    Some(DownloadFromContainerOptions {
+                        path: container_path.to_string(),
+                    }),
+                )
+                .map_err(std::io::Error::other),
+        );
+
+        let file = tokio::fs::File::create(host_path).await.map_err(|e| {
+            Error::Message(format!(
+                "failed to create `{host_path}`: {e}",
+                host_path = host_path.display()
+            ))
+        })?;
+
+        let mut writer = tokio::io::BufWriter::new(file);
+        if let Err(e) = extract_file_to(archive, container_path, &mut writer).await {
+            drop(writer);
+            if let Err(e) = tokio::fs::remove_file(host_path).await {
+                warn!(
+                    "failed to remove `{host_path}`: {e}",
+                    host_path = host_path.display()
+                );
+            }
+
+            return Err(download_error(container_path, e));
+        }
+
+        Ok(())
+    }
+
+    /// Downloads the contents of a file from the container and verifies its
+    /// SHA-256 digest.
+    ///
+    /// This is [`Self::download_file()`] followed by a check of the digest of
+    /// the contents against `expected_sha256`, given as hexadecimal (in either
+    /// case). If the digests differ, such as because the file was truncated,
+    /// [`Error::ChecksumMismatch`] is returned with the computed digest.
+    pub async fn download_file_verified(
+        &self,
+        path: &str,
+        expected_sha256: &str,
+    ) -> Result<Vec<u8>> {
+        let contents = self.download_file(path).await?;
+        verify_sha256(path, &contents, expected_sha256)?;
+        Ok(contents)
+    }
+
+    /// Starts a command within the running container without waiting for it.
+    ///
+    /// The output of the command is discarded. Use the returned
+    /// [`ExecHandle`] to check whether the command is still running and to
+    /// get its exit code once it has exited.
+    pub async fn exec(
+        &self,
+        cmd: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<ExecHandle> {
+        let handle = self
+            .create_exec(cmd.into_iter().map(Into::into).collect(), false)
+            .await?;
+
+        debug!(
+            "starting exec `{id}` in container `{name}`",
+            id = handle.id(),
+            name = self.name
+        );
+
+        self.client
+            .start_exec(
+                handle.id(),
+                Some(StartExecOptions {
+                    detach: true,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .map_err(Error::from)?;
+
+        Ok(handle)
+    }
+
+    /// Creates an exec instance for a command within the running container.
+    ///
+    /// If `attach` is set, the output of the command is streamed back when
+    /// the exec instance is started; otherwise, it is discarded.
+    async fn create_exec(&self, cmd: Vec<String>, attach: bool) -> Result<ExecHandle> {
+        let exec = self
+            .client
+            .create_exec(
+                &self.name,
+                CreateExecOptions {
+                    cmd: Some(cmd),
+                    attach_stdout: Some(attach),
+                    attach_stderr: Some(attach),
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(Error::from)?;
+
+        Ok(ExecHandle::new(
+            self.client.clone(),
+            self.name.clone(),
+            exec.id,
+        ))
+    }
+
+    /// Runs a command within the running container and collects its output.
+    async fn exec_output(&self, cmd: impl IntoIterator<Item = &str>) -> Result<Output> {
+        let handle = self
+            .create_exec(cmd.into_iter().map(String::from).collect(), true)
+            .await?;
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        if let StartExecResults::Attached { mut output, .. } = self
+            .client
+            .start_exec(handle.id(), None)
+            .await
+            .map_err(Error::from)?
+        {
+            while let Some(log) = output.next().await {
+                match log? {
+                    LogOutput::StdOut { message } => stdout.extend(&message),
+                    LogOutput::StdErr { message } => stderr.extend(&message),
+                    v => trace!("unhandled log message: {v:?}"),
+                }
+            }
+        }
+
+        let status = handle.inspect().await?.exit_status().ok_or_else(|| {
+            Error::Message(format!(
+                "Docker did not report an exit code for exec `{id}` in container `{name}`",
+                id = handle.id(),
+                name = self.name
+            ))
+        })?;
+
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Checks whether a path within the container is an executable file.
+    ///
+    /// Only the header of the path's archive is read, so the file itself is
+    /// not downloaded. Symbolic links are not followed and are assumed to be
+    /// executable.
+    pub(crate) async fn is_executable(&self, path: &str) -> Result<bool> {
+        let mut stream = self.client.download_from_container(
+            &self.name,
+            Some(DownloadFromContainerOptions {
+                path: path.to_string(),
+            }),
+        );
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        while header.len() < HEADER_LEN {
+            match stream.next().await {
+                Some(Ok(bytes)) => header.extend_from_slice(&bytes),
+                Some(Err(e)) => {
+                    return match Error::from(e) {
+                        Error::NotFound(_) => Ok(false),
+                        e => Err(e),
+                    };
+                }
+                None => return Ok(false),
+            }
+        }
+
+        Ok(is_executable_entry(&header[..HEADER_LEN]))
+    }
+
+    /// Starts the container.
+    ///
+    /// This does not wait for the container to exit. Typically, you will want
+    /// to use [`Self::run()`] instead.
+    pub async fn start(&self) -> Result<()> {
+        debug!("starting container `{name}`", name = self.name);
+
+        self.client
+            .start_container(&self.name, None::<StartContainerOptions>)
+            .await
+            .map_err(Error::from)?;
+
+        self.observe(|observer, name, at| observer.started(name, at));
+        Ok(())
+    }
+
+    /// Attaches to the container with the specified options.
+    ///
+    /// This returns the stream of output frames from the container as they are
+    /// received, leaving any buffering or processing to the caller. The
+    /// container is not started: call [`Self::start()`] after attaching so that
+    /// no output is missed.
+    ///
+    /// The Docker daemon attaches to the container's streams before it
+    /// upgrades the connection, and this only returns once the connection has
+    /// been upgraded. Everything the container writes after it is started,
+    /// including its very first byte, is therefore sent on the returned
+    /// stream. Output written before attaching (for example, by a container
+    /// that was already running) is not; read it with the container's logs
+    /// instead.
+    ///
+    /// This is what [`Self::run()`] uses to collect the container's output.
+    pub async fn attach(
+        &self,
+        options: AttachContainerOptions,
+    ) -> Result<impl Stream<Item = Result<LogOutput>> + Send + use<>> {
+        let results = self
+            .client
+            .attach_container(&self.name, Some(options))
+            .await
+            .map_err(Error::from)?;
+
+        // The output stream is always present in the attach results: bollard
+        // returns an error rather than results if the daemon does not upgrade
+        // the connection, so there is nothing to unwrap here
+        Ok(results.output.map_err(Error::from))
+    }
+
+    /// Attaches to the container, returning its output as a stream of
+    /// [`Frame`](crate::logs::Frame)s in the order they are received.
+    ///
+    /// The streams attached to are those the container was created to attach
+    /// (see [`Builder::attach_stdout()`] and [`Builder::attach_stderr()`]). As
+    /// with [`Self::attach()`], the container is not started: call
+    /// [`Self::start()`] after attaching so that no output is missed.
+    pub async fn attach_frames(
+        &self,
+    ) -> Result<impl Stream<Item = Result<crate::logs::Frame>> + Send + use<>> {
+        let stream = self
+            .attach(AttachContainerOptions {
+                stdout: self.attach_stdout,
+                stderr: self.attach_stderr,
+                stream: true,
+                ..Default::default()
+            })
+            .await?;
+
+        Ok(crate::logs::Frame::frames(stream))
+    }
+
+    /// Attaches to the standard input, standard output, and standard error of
+    /// the container.
+    ///
+    /// This returns a writer for the container's standard input and a reader
+    /// for its output. Unlike [`Self::run()`], no output is buffered and the
+    /// container is not started: call [`Self::start()`] after attaching so that
+    /// no output is missed.
+    ///
+    /// For an interactive session, the container should be created with
+    /// [`Builder::tty()`] and [`Builder::attach_stdin()`]. In TTY mode, the
+    /// reader yields the raw terminal output.
+    ///
+    /// Without a TTY, the Docker daemon multiplexes standard output and
+    /// standard error into frames, each consisting of an 8-byte header (the
+    /// stream type followed by the big-endian payload length) and the payload.
+    /// The frames are decoded before reaching the reader, which yields the
+    /// payloads of both streams interleaved in the order they were received, so
+    /// the two streams cannot be told apart.
+    pub async fn attach_interactive(
+        &self,
+    ) -> Result<(
+        impl AsyncWrite + Send + Unpin + use<>,
+        impl AsyncRead + Send + Unpin + use<>,
+    )> {
+        let results = self
+            .client
+            .attach_container(
+                &self.name,
+                Some(AttachContainerOptions {
+                    stdin: true,
+                    stdout: true,
+                    stderr: true,
+                    stream: true,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .map_err(Error::from)?;
+
+        let output = results
+            .output
+            .map_ok(LogOutput::into_bytes)
+            .map_err(std::io::Error::other);
+
+        Ok((results.input, StreamReader::new(output)))
+    }
+
+    /// Runs a container and waits for the execution to end.
+    pub async fn run(&self, started: impl FnOnce()) -> Result<Output> {
+        Ok(self
+            .run_with_options(RunOptions::default(), started)
+            .await?
+            .output)
+    }
+
+    /// Runs a container and waits for the execution to end, returning an error
+    /// if it exits with a non-zero exit code.
+    ///
+    /// This is [`Self::run()`] for the common case of treating failure as an
+    /// error: [`Error::NonZeroExit`] is returned with the container's exit code
+    /// and standard error.
+    pub async fn run_checked(&self, started: impl FnOnce()) -> Result<Output> {
+        let output = self.run(started).await?;
+        check_exit(&output)?;
+        Ok(output)
+    }
+
+    /// Runs a container and waits for the execution to end, collecting
+    /// standard output and standard error into a single buffer.
+    ///
+    /// The output is interleaved in the order it was received from the Docker
+    /// daemon. See [`RunOptions::merge_output()`].
+    pub async fn run_combined(&self, started: impl FnOnce()) -> Result<(ExitStatus, Vec<u8>)> {
+        let output = self
+            .run_with_options(RunOptions::default().merge_output(), started)
+            .await?
+            .output;
+
+        Ok((output.status, output.stdout))
+    }
+
+    /// Runs a container and waits for the execution to end, writing standard
+    /// output and standard error to files on the host as they are received.
+    ///
+    /// Unlike [`Self::run()`], the output is never held in memory, so this is
+    /// suited to long runs whose complete output may not fit. The files are
+    /// created, or truncated if they exist, before the container is started
+    /// and are flushed and synced to disk before the exit status is returned.
+    /// Only the streams the container was created to attach are written; the
+    /// file of an unattached stream is left empty.
+    pub async fn run_to_files(
+        &self,
+        stdout_path: impl AsRef<Path>,
+        stderr_path: impl AsRef<Path>,
+        started: impl FnOnce(),
+    ) -> Result<ExitStatus> {
+        let (stdout_path, stderr_path) = (stdout_path.as_ref(), stderr_path.as_ref());
+        let write_error = |path: &Path, e: std::io::Error| {
+            Error::Message(format!(
+                "failed to write output of container `{name}` to `{path}`: {e}",
+                name = self.name,
+                path = path.display()
+            ))
+        };
+
+        let mut stdout = tokio::io::BufWriter::new(
+            tokio::fs::File::create(stdout_path)
+                .await
+                .map_err(|e| write_error(stdout_path, e))?,
+        );
+        let mut stderr = tokio::io::BufWriter::new(
+            tokio::fs::File::create(stderr_path)
+                .await
+                .map_err(|e| write_error(stderr_path, e))?,
+        );
+
+        // Attach before starting so that no output is missed; see `attach()`
+        let mut stream = pin!(
+            self.attach(AttachContainerOptions {
+                stdout: self.attach_stdout,
+                stderr: self.attach_stderr,
+                stream: true,
+                ..Default::default()
+            })
+            .await?
+        );
+
+        self.start().await?;
+        started();
+
+        while let Some(log) = stream.next().await {
+            let (file, path, message) = match log? {
+                LogOutput::StdOut { message } => (&mut stdout, stdout_path, message),
+                LogOutput::StdErr { message } => (&mut stderr, stderr_path, message),
+                v => {
+                    trace!("unhandled log message: {v:?}");
+                    continue;
+                }
+            };
+
+            file.write_all(&message)
+                .await
+                .map_err(|e| write_error(path, e))?;
+        }
+
+        let exit_code = self.wait_exit_code().await?;
+        self.observe(|observer, name, at| observer.exited(name, exit_code, at));
+
+        for (mut file, path) in [(stdout, stdout_path), (stderr, stderr_path)] {
+            file.flush().await.map_err(|e| write_error(path, e))?;
+            file.into_inner()
+                .sync_all()
+                .await
+                .map_err(|e| write_error(path, e))?;
+        }
+
+        Ok(exit_status(exit_code))
+    }
+
+    /// Runs a container with the specified options and waits for the execution
+    /// to end.
+    ///
+    /// The container is attached to before it is started (see
+    /// [`Self::attach()`]), so all of its output is collected, however quickly
+    /// it exits. The container must therefore not have been started already.
+    pub async fn run_with_options(
+        &self,
+        mut options: RunOptions,
+        started: impl FnOnce(),
+    ) -> Result<RunOutput> {
+        // Attach to the logs stream. This must happen before the container is
+        // started: attaching completes only once the daemon is forwarding the
+        // container's streams, so no early output can be lost.
+        let mut stream = pin!(
+            self.attach(AttachContainerOptions {
+                stdout: options.attach_stdout.unwrap_or(self.attach_stdout),
+                stderr: options.attach_stderr.unwrap_or(self.attach_stderr),
+                stream: true,
+                ..Default::default()
+            })
+            .await?
+        );
+
+        // Start the container.
+        self.start().await?;
+
+        // Notify that the container has started
+        started();
+
+        // Sample statistics and send heartbeats until the container exits; the
+        // tasks are aborted when their guards are dropped, including on an early
+        // return
+        let run_stats = Arc::new(Mutex::new(RunStats::default()));
+        let sampler = options.stats.take().map(|sampler| {
+            let client = self.client.clone();
+            let name = self.name.clone();
+            let run_stats = run_stats.clone();
+
+            AbortOnDrop(tokio::spawn(sample_stats(client, name, sampler, run_stats)))
+        });
+        let heartbeat = options
+            .heartbeat
+            .take()
+            .map(|heartbeat| AbortOnDrop(tokio::spawn(send_heartbeats(heartbeat))));
+
+        // Collect standard out/standard err.
+        let mut stdout = Vec::<u8>::with_capacity(0x0FFF);
+        let mut stderr = Vec::<u8>::with_capacity(0x0FFF);
+        let mut collected = 0;
+        let mut dropped_bytes = 0;
+
+        loop {
+            let log = match options.idle_timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, stream.next()).await {
+                    Ok(log) => log,
+                    Err(_) => {
+                        debug!(
+                            "container `{name}` produced no output for {timeout:?}",
+                            name = self.name
+                        );
+
+                        // The container may exit just as the timeout fires, in
+                        // which case it is no longer running (409) or has been
+                        // removed (404) and there is nothing to kill
+                        match self.kill().await {
+                            Ok(()) | Err(Error::Conflict(_) | Error::NotFound(_)) => {}
+                            Err(e) => warn!(
+                                "failed to kill idle container `{name}`: {e}",
+                                name = self.name
+                            ),
+                        }
+
+                        return Err(Error::IdleTimeout(timeout));
+                    }
+                },
+                None => stream.next().await,
+            };
+
+            let Some(log) = log else {
+                break;
+            };
+
+            let log = log?;
+            if options
+                .filter
+                .as_ref()
+                .is_some_and(|filter| !(filter.0)(&log))
+            {
+                continue;
+            }
+
+            let (is_stderr, message) = match log {
+                LogOutput::StdOut { message } => (false, message),
+                LogOutput::StdErr { message } => (true, message),
+                v => {
+                    trace!("unhandled log message: {v:?}");
+                    continue;
+                }
+            };
+
+            if let Some(tee) = &mut options.tee {
+                let writer = if is_stderr {
+                    &mut tee.stderr
+                } else {
+                    &mut tee.stdout
+                };
+
+                writer
+                    .write_all(&message)
+                    .await
+                    .map_err(|e| tee_error(&self.name, e))?;
+            }
+
+            let buffer = if is_stderr && !options.merge_output {
+                &mut stderr
+            } else {
+                &mut stdout
+            };
+
+            let kept = match options.max_output_bytes {
+                Some(max) => message.len().min(max - collected),
+                None => message.len(),
+            };
+
+            buffer.extend(&message[..kept]);
+            collected += kept;
+
+            if kept < message.len() {
+                if dropped_bytes == 0 {
+                    debug!(
+                        "output of container `{name}` exceeded the limit of {collected} bytes",
+                        name = self.name
+                    );
+
+                    if options.stop_on_truncation {
+                        self.stop(None).await?;
+                    }
+                }
+
+                dropped_bytes += (message.len() - kept) as u64;
+            }
+        }
+
+        if let Some(tee) = &mut options.tee {
+            for writer in [&mut tee.stdout, &mut tee.stderr] {
+                writer.flush().await.map_err(|e| tee_error(&self.name, e))?;
+            }
+        }
+
+        let exit_code = self.wait_exit_code().await?;
+        let sampled = sampler.is_some();
+        drop(sampler);
+        drop(heartbeat);
+        self.observe(|observer, name, at| observer.exited(name, exit_code, at));
 
-    /// Whether or not standard output is attached.
-    attach_stdout: bool,
+        let output = Output {
+            status: exit_status(exit_code),
+            stdout,
+            stderr,
+        };
 
-    /// Whether or not standard output is attached.
-    attach_stderr: bool,
-}
+        // SAFETY: the lock is never held across a panic.
+        let stats = sampled.then(|| run_stats.lock().unwrap().clone());
+        Ok(RunOutput {
+            output,
+            dropped_bytes,
+            peak_memory: stats.as_ref().and_then(|stats| stats.peak_memory),
+            stats,
+        })
+    }
 
-impl Container {
-    /// Creates a new [`Container`] if you already know the name of a container.
-    ///
-    /// You should typically use [`Self::builder()`] unless you receive the
-    /// container name externally from a user (say, on the command line as an
-    /// argument).
-    pub fn new(client: Docker, name: String, attach_stdout: bool, attach_stderr: bool) -> Self {
-        Self {
-            client,
-            name,
-            attach_stdout,
-            attach_stderr,
+    /// Waits for the container to exit and gets its exit code.
+    async fn wait_exit_code(&self) -> Result<i64> {
+        debug!("waiting for container `{name}` to exit", name = self.name);
+        let mut wait_stream = self
+            .client
+            .wait_container(&self.name, None::<WaitContainerOptions>);
+
+        if let Some(result) = wait_stream.next().await {
+            match result {
+                // Bollard turns non-zero exit codes into wait errors, so check for both
+                Ok(ContainerWaitResponse {
+                    status_code: code, ..
+                })
+                | Err(bollard::errors::Error::DockerContainerWaitError { code, .. }) => {
+                    return Ok(code);
+                }
+                Err(e) => return Err(e.into()),
+            }
         }
+
+        // Get the exit code if the wait was immediate
+        let container = self.inspect().await?;
+
+        container
+            .state
+            .and_then(|state| state.exit_code)
+            .ok_or_else(|| {
+                Error::Message(format!(
+                    "Docker did not report an exit code for container `{name}`",
+                    name = self.name
+                ))
+            })
     }
 
-    /// Uploads an input file to the container.
-    pub async fn upload_file(&self, path: &str, contents: &[u8]) -> Result<()> {
-        let mut tar = tar::Builder::new(Vec::with_capacity(DEFAULT_TAR_CAPACITY));
-        let path = path.trim_start_matches("/");
+    /// Waits for the first of the given containers to exit.
+    ///
+    /// Returns the index of the container within `containers` and its exit
+    /// code. The waits on the remaining containers are dropped once one has
+    /// exited; the remaining containers are otherwise left running, so they
+    /// can be stopped or removed as needed.
+    ///
+    /// Returns an error if `containers` is empty or if waiting on any
+    /// container fails before one exits.
+    pub async fn wait_any(containers: &[&Container]) -> Result<(usize, i64)> {
+        if containers.is_empty() {
+            return Err(Error::Message(String::from(
+                "no containers were provided to wait on",
+            )));
+        }
 
-        let mut header = tar::Header::new_gnu();
-        header.set_path(path).unwrap();
-        header.set_size(contents.len() as u64);
-        header.set_mode(0o644);
+        let waits = containers
+            .iter()
+            .map(|container| Box::pin(container.wait_exit_code()));
+        let (result, index, remaining) = futures::future::select_all(waits).await;
 
-        // SAFETY: this is manually crafted to always unwrap.
-        tar.append_data(&mut header, path, Cursor::new(contents))
-            .unwrap();
+        // Dropping the remaining waits closes their connections to the daemon
+        drop(remaining);
+
+        let exit_code = result?;
+        debug!(
+            "container `{name}` exited first with exit code {exit_code}",
+            name = containers[index].name
+        );
+
+        Ok((index, exit_code))
+    }
+
+    /// Stops the container.
+    ///
+    /// The container is sent a `SIGTERM` and, if it has not exited after
+    /// `timeout`, a `SIGKILL`. If no timeout is provided, the container's
+    /// configured stop timeout is used.
+    pub async fn stop(&self, timeout: Option<Duration>) -> Result<()> {
+        debug!("stopping container `{name}`", name = self.name);
 
         self.client
-            .upload_to_container(
+            .stop_container(
                 &self.name,
-                Some(UploadToContainerOptions {
-                    path: String::from("/"),
+                Some(StopContainerOptions {
+                    t: timeout.map(|t| i32::try_from(t.as_secs()).unwrap_or(i32::MAX)),
                     ..Default::default()
                 }),
-                // SAFETY: this is manually crafted to always unwrap.
-                body_full(tar.into_inner().unwrap().into()),
             )
             .await
-            .map_err(Error::Docker)
+            .map_err(Error::from)
     }
 
-    /// Runs a container and waits for the execution to end.
-    pub async fn run(&self, started: impl FnOnce()) -> Result<Output> {
-        // Attach to the logs stream.
-        let stream = self
-            .client
-            .attach_container(
-                &self.name,
-                Some(AttachContainerOptions {
-                    stdout: self.attach_stdout,
-                    stderr: self.attach_stderr,
-                    stream: true,
-                    ..Default::default()
-                }),
-            )
-            .await
-            .map_err(Error::Docker)?
-            .output;
+    /// Checkpoints the running container with CRIU, stopping it.
+    ///
+    /// This is `docker checkpoint create`: the state of the container's
+    /// processes is saved as the checkpoint `name`, from which the container
+    /// can be resumed with [`Self::restore()`]. The checkpoint is stored with
+    /// the container, so the container must not be removed in between. Names
+    /// are at least two characters of ASCII letters, digits, `_`, `.`, and
+    /// `-`, starting with a letter or digit.
+    ///
+    /// Checkpoints require experimental features of the Docker daemon (see
+    /// [`Docker::experimental()`](crate::Docker::experimental)) and CRIU on
+    /// its host. As [`bollard`] does not support them, the request is sent to
+    /// the Docker daemon directly, which requires a client created with
+    /// [`Docker::with_defaults()`](crate::Docker::with_defaults) or a similar
+    /// constructor that connects over a Unix socket or unencrypted TCP.
+    /// [`Error::Unsupported`] is returned if either requirement is not met.
+    pub async fn checkpoint(&self, name: &str) -> Result<()> {
+        let address = self.checkpoint_address(name).await?;
 
-        debug!("starting container `{name}`", name = self.name);
+        debug!(
+            "checkpointing container `{container}` as `{name}`",
+            container = self.name
+        );
 
-        // Start the container.
-        self.client
-            .start_container(&self.name, None::<StartContainerOptions>)
+        address
+            .send(
+                Method::POST,
+                &format!(
+                    "/v{version}/containers/{container}/checkpoints",
+                    version = self.client.client_version(),
+                    container = self.name
+                ),
+                Some(serde_json::json!({ "CheckpointID": name, "Exit": true })),
+            )
             .await
-            .map_err(Error::Docker)?;
+    }
 
-        // Notify that the container has started
-        started();
+    /// Restores the stopped container from a checkpoint created with
+    /// [`Self::checkpoint()`].
+    ///
+    /// This is `docker start --checkpoint`: the container is started with
+    /// the state of its processes when the checkpoint was created. The same
+    /// requirements as for [`Self::checkpoint()`] apply.
+    pub async fn restore(&self, name: &str) -> Result<()> {
+        let address = self.checkpoint_address(name).await?;
 
-        // Collect standard out/standard err.
-        let (stdout, stderr) = stream
-            .try_fold(
-                (
-                    Vec::<u8>::with_capacity(0x0FFF),
-                    Vec::<u8>::with_capacity(0x0FFF),
-                ),
-                |(mut stdout, mut stderr), log| async move {
-                    match log {
-                        LogOutput::StdOut { message } => {
-                            stdout.extend(&message);
-                        }
-                        LogOutput::StdErr { message } => {
-                            stderr.extend(&message);
-                        }
-                        v => {
-                            trace!("unhandled log message: {v:?}")
-                        }
-                    }
+        debug!(
+            "restoring container `{container}` from checkpoint `{name}`",
+            container = self.name
+        );
 
-                    Ok((stdout, stderr))
-                },
+        address
+            .send(
+                Method::POST,
+                &format!(
+                    "/v{version}/containers/{container}/start?checkpoint={name}",
+                    version = self.client.client_version(),
+                    container = self.name
+                ),
+                None,
             )
-            .await
-            .map_err(Error::Docker)?;
+            .await?;
 
-        // Wait for the container to be completed.
+        self.observe(|observer, name, at| observer.started(name, at));
+        Ok(())
+    }
 
-        debug!("waiting for container `{name}` to exit", name = self.name);
-        let mut wait_stream = self
+    /// Gets the address of the Docker daemon to send a request for a
+    /// checkpoint to, checking that the daemon supports checkpoints.
+    async fn checkpoint_address(&self, name: &str) -> Result<&DaemonAddress> {
+        if !is_valid_name(name) {
+            return Err(Error::Message(format!(
+                "`{name}` is not a valid checkpoint name"
+            )));
+        }
+
+        let address = self.address.as_ref().ok_or_else(|| {
+            Error::Unsupported(String::from(
+                "checkpoints require a client created with `Docker::with_defaults()` (or a \
+                 similar constructor) that connects to the Docker daemon over a Unix socket or \
+                 unencrypted TCP",
+            ))
+        })?;
+
+        let experimental = self
             .client
-            .wait_container(&self.name, None::<WaitContainerOptions>);
+            .version()
+            .await
+            .map_err(Error::from)?
+            .experimental
+            .unwrap_or_default();
+        if !experimental {
+            return Err(Error::Unsupported(String::from(
+                "checkpoints require the Docker daemon to have experimental features enabled",
+            )));
+        }
 
-        let mut exit_code = None;
-        if let Some(result) = wait_stream.next().await {
-            match result {
-                // Bollard turns non-zero exit codes into wait errors, so check for both
-                Ok(ContainerWaitResponse {
-                    status_code: code, ..
-                })
-                | Err(bollard::errors::Error::DockerContainerWaitError { code, .. }) => {
-                    exit_code = Some(code);
+        Ok(address)
+    }
+
+    /// Follows the container's logs, reconnecting whenever the stream is
+    /// dropped (for example, because the Docker daemon restarted).
+    ///
+    /// Reconnections are retried with a jittered, capped exponential backoff;
+    /// see [`ReconnectOptions`](crate::reconnect::ReconnectOptions). If
+    /// `timestamps` is set, each line is prefixed with the time it was logged.
+    /// The stream ends once the container has exited and all of its logs have
+    /// been received, or if the container is removed.
+    pub fn logs_reconnecting(
+        &self,
+        timestamps: bool,
+        reconnect: crate::reconnect::ReconnectOptions,
+    ) -> impl Stream<Item = Result<LogOutput>> + use<> {
+        crate::reconnect::logs(
+            self.client.clone(),
+            self.name.clone(),
+            timestamps,
+            reconnect,
+        )
+    }
+
+    /// Gets the complete standard output and standard error of the container.
+    ///
+    /// Unlike [`Self::run()`], which collects output by attaching to the
+    /// container as it runs, this fetches the output the Docker daemon has
+    /// logged so far. It can be used on a container that was started without
+    /// being attached to, including after it has exited, so long as it has not
+    /// been removed. When the container has a TTY, all output is returned as
+    /// standard output.
+    pub async fn logs_all(&self) -> Result<(Vec<u8>, Vec<u8>)> {
+        debug!("getting logs of container `{name}`", name = self.name);
+
+        let mut stream = self.client.logs(
+            &self.name,
+            Some(LogsOptions {
+                stdout: true,
+                stderr: true,
+                tail: String::from("all"),
+                ..Default::default()
+            }),
+        );
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        while let Some(log) = stream.next().await {
+            match log.map_err(Error::from)? {
+                LogOutput::StdOut { message } | LogOutput::Console { message } => {
+                    stdout.extend(&message)
                 }
-                Err(e) => return Err(e.into()),
+                LogOutput::StdErr { message } => stderr.extend(&message),
+                LogOutput::StdIn { .. } => {}
             }
         }
 
-        if exit_code.is_none() {
-            // Get the exit code if the wait was immediate
-            let container = self
-                .client
-                .inspect_container(&self.name, None::<InspectContainerOptions>)
-                .await
-                .map_err(Error::Docker)?;
+        Ok((stdout, stderr))
+    }
 
-            exit_code = Some(
-                container
-                    .state
-                    .expect("Docker reported a container without a state")
-                    .exit_code
-                    .expect("Docker reported a finished contained without an exit code"),
-            );
+    /// Checks whether the container exists.
+    ///
+    /// The container is inspected, and `false` is returned if the Docker
+    /// daemon responds that it does not exist (status code 404); any other
+    /// error is returned. Containers that are being removed still exist until
+    /// their removal completes.
+    pub async fn exists(&self) -> Result<bool> {
+        match self.inspect().await {
+            Ok(_) => Ok(true),
+            Err(Error::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
         }
+    }
 
-        #[cfg(unix)]
-        let output = Output {
-            // See WEXITSTATUS from wait(2) to explain the shift
-            status: ExitStatus::from_raw((exit_code.unwrap() as i32) << 8),
-            stdout,
-            stderr,
-        };
+    /// Inspects the container.
+    pub async fn inspect(&self) -> Result<ContainerInspectResponse> {
+        self.client
+            .inspect_container(&self.name, None::<InspectContainerOptions>)
+            .await
+            .map_err(Error::from)
+    }
 
-        #[cfg(windows)]
-        let output = Output {
-            status: ExitStatus::from_raw(exit_code.unwrap() as u32),
-            stdout,
-            stderr,
-        };
+    /// Gets the number of times the container has been restarted by the Docker
+    /// daemon.
+    ///
+    /// This only increases when the container's restart policy (see
+    /// [`Builder::restart_policy()`]) causes the daemon to restart it, so a
+    /// steadily increasing count is a sign of a crash-looping container.
+    pub async fn restart_count(&self) -> Result<i64> {
+        Ok(self.inspect().await?.restart_count.unwrap_or_default())
+    }
 
-        Ok(output)
+    /// Gets the last error reported by the Docker daemon for the container.
+    ///
+    /// When a container fails to start, this explains why (e.g., `exec format
+    /// error` or `no such file or directory` for a program that cannot be
+    /// run), which is often more useful to report than the exit code alone.
+    ///
+    /// Returns `Ok(None)` if the daemon has not reported an error.
+    pub async fn last_error(&self) -> Result<Option<String>> {
+        Ok(self
+            .inspect()
+            .await?
+            .state
+            .and_then(|state| state.error)
+            .filter(|error| !error.is_empty()))
+    }
+
+    /// Gets the labels of the container.
+    ///
+    /// Labels cannot be changed once a container is created, so these are the
+    /// labels set with [`Builder::label()`] (along with any inherited from the
+    /// image), which makes them suitable for recovering identifiers such as a
+    /// run ID after a restart of the process that created the container.
+    pub async fn labels(&self) -> Result<HashMap<String, String>> {
+        Ok(self
+            .inspect()
+            .await?
+            .config
+            .and_then(|config| config.labels)
+            .unwrap_or_default())
+    }
+
+    /// Gets the times the container was created, last started, and last
+    /// exited.
+    ///
+    /// The times are parsed from the container's inspection; see
+    /// [`ContainerTimes`] for more information.
+    pub async fn timestamps(&self) -> Result<ContainerTimes> {
+        Ok(ContainerTimes::from_inspect(&self.inspect().await?))
+    }
+
+    /// Streams the status transitions of the container (e.g., from created to
+    /// running to exited).
+    ///
+    /// The current status is yielded first, followed by each change in status
+    /// as reported by the Docker daemon's events, so the status can be shown
+    /// live without polling [`Self::inspect()`]. The stream ends once the
+    /// container has been removed, after yielding
+    /// [`ContainerStatus::Removed`](crate::events::ContainerStatus::Removed),
+    /// or after the first error.
+    pub fn status_stream(
+        &self,
+    ) -> impl Stream<Item = Result<crate::events::ContainerStatus>> + use<> {
+        crate::events::status_stream(self.client.clone(), self.name.clone())
+    }
+
+    /// Gets the mounts of the container as reported by the Docker daemon.
+    ///
+    /// Each mount includes its type (such as a bind mount or volume), source
+    /// on the host, destination within the container, and mode, which is
+    /// useful for checking that binds and volumes were mounted where expected.
+    pub async fn mounts(&self) -> Result<Vec<MountPoint>> {
+        Ok(self.inspect().await?.mounts.unwrap_or_default())
+    }
+
+    /// Gets the IP address of the container on a network.
+    ///
+    /// If `network` is `None`, the address on the default `bridge` network is
+    /// returned.
+    ///
+    /// Returns `Ok(None)` if the container is not connected to the network or
+    /// has not yet been assigned an address on it (for example, because it has
+    /// not been started).
+    pub async fn ip_address(&self, network: Option<&str>) -> Result<Option<IpAddr>> {
+        let network = network.unwrap_or("bridge");
+
+        let address = self
+            .inspect()
+            .await?
+            .network_settings
+            .and_then(|settings| settings.networks)
+            .and_then(|mut networks| networks.remove(network))
+            .and_then(|endpoint| endpoint.ip_address)
+            .filter(|address| !address.is_empty());
+
+        address
+            .map(|address| {
+                address.parse().map_err(|e| {
+                    Error::Message(format!(
+                        "Docker reported an invalid IP address `{address}` for container \
+                         `{name}`: {e}",
+                        name = self.name
+                    ))
+                })
+            })
+            .transpose()
+    }
+
+    /// Connects the container to a network with the given aliases on that
+    /// network.
+    ///
+    /// The container may be running, in which case it gains connectivity on
+    /// the network immediately. Together with [`Self::disconnect_network()`],
+    /// this can simulate a network partition: reconnecting with the same
+    /// aliases restores the container's name resolution on the network,
+    /// although it may be assigned a different IP address.
+    pub async fn connect_network(
+        &self,
+        network: &str,
+        aliases: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<()> {
+        debug!(
+            "connecting container `{name}` to network `{network}`",
+            name = self.name
+        );
+
+        let aliases: Vec<_> = aliases.into_iter().map(Into::into).collect();
+        self.client
+            .connect_network(
+                network,
+                NetworkConnectRequest {
+                    container: Some(self.name.clone()),
+                    endpoint_config: Some(endpoint_settings(&aliases)),
+                },
+            )
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Disconnects the container from a network.
+    ///
+    /// The container may be running, in which case it immediately loses
+    /// connectivity on the network; see [`Self::connect_network()`] to
+    /// reconnect it.
+    pub async fn disconnect_network(&self, network: &str) -> Result<()> {
+        debug!(
+            "disconnecting container `{name}` from network `{network}`",
+            name = self.name
+        );
+
+        self.client
+            .disconnect_network(
+                network,
+                NetworkDisconnectRequest {
+                    container: Some(self.name.clone()),
+                    force: None,
+                },
+            )
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Restarts the container.
+    ///
+    /// If the container is running, it is first stopped, waiting up to
+    /// `timeout` for it to exit before it is killed. If no timeout is provided,
+    /// the container's configured stop timeout is used.
+    ///
+    /// If the container is not running, the Docker daemon simply starts it.
+    pub async fn restart(&self, timeout: Option<Duration>) -> Result<()> {
+        debug!("restarting container `{name}`", name = self.name);
+
+        self.client
+            .restart_container(
+                &self.name,
+                Some(RestartContainerOptions {
+                    t: timeout.map(|t| i32::try_from(t.as_secs()).unwrap_or(i32::MAX)),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Kills the container by sending it a `SIGKILL`.
+    pub async fn kill(&self) -> Result<()> {
+        debug!("killing container `{name}`", name = self.name);
+
+        self.client
+            .kill_container(&self.name, None::<KillContainerOptions>)
+            .await
+            .map_err(Error::from)
     }
 
     /// Removes a container with the level of force specified.
@@ -222,8 +1917,9 @@ impl Container {
                 }),
             )
             .await
-            .map_err(Error::Docker)?;
+            .map_err(Error::from)?;
 
+        self.observe(|observer, name, at| observer.removed(name, at));
         Ok(())
     }
 
@@ -244,4 +1940,300 @@ impl Container {
         debug!("force removing container: `{}`", self.name);
         self.remove_inner(true).await
     }
+
+    /// Consumes `self` and returns a guard that force removes the container
+    /// when dropped.
+    ///
+    /// See [`ContainerGuard`] for more information.
+    pub fn guard(self) -> ContainerGuard {
+        ContainerGuard::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitized_names() {
+        let name = sanitize_name("my job/1");
+        assert!(name.starts_with("my-job-1-"));
+        assert_eq!(name.len(), "my-job-1-".len() + NAME_SUFFIX_LEN);
+        assert!(is_valid_name(&name));
+
+        let name = sanitize_name("--.task_");
+        assert!(name.starts_with("task_"));
+        assert!(is_valid_name(&name));
+
+        assert_eq!(sanitize_name("").len(), NAME_SUFFIX_LEN);
+        assert_eq!(sanitize_name("/").len(), NAME_SUFFIX_LEN);
+        assert_ne!(sanitize_name("job"), sanitize_name("job"));
+    }
+
+    #[test]
+    fn valid_names() {
+        assert!(is_valid_name("checkpoint-1"));
+        assert!(is_valid_name("a.b_c"));
+        assert!(!is_valid_name("a"));
+        assert!(!is_valid_name("-checkpoint"));
+        assert!(!is_valid_name("check point"));
+        assert!(!is_valid_name("a&b=c"));
+    }
+
+    #[tokio::test]
+    async fn checkpoint_without_address() {
+        let client =
+            Docker::connect_with_http("http://localhost:2375", 1, bollard::API_DEFAULT_VERSION)
+                .unwrap();
+        let container = Container::new(client, String::from("app"), false, false);
+
+        assert!(matches!(
+            container.checkpoint("checkpoint").await,
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            container.restore("checkpoint").await,
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            container.checkpoint("a b").await,
+            Err(Error::Message(_))
+        ));
+    }
+
+    #[test]
+    fn dir_sizes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a"), [0; 10]).unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub").join("b"), [0; 5]).unwrap();
+
+        assert_eq!(dir_size(dir.path()).unwrap(), 15);
+        assert!(matches!(
+            check_dir_size(dir.path(), Some(14)),
+            Err(Error::UploadTooLarge {
+                size: 15,
+                limit: 14
+            })
+        ));
+        assert!(check_dir_size(dir.path(), Some(15)).is_ok());
+        assert!(check_dir_size(dir.path(), None).is_ok());
+    }
+
+    #[cfg(feature = "async-tar")]
+    #[tokio::test]
+    async fn streamed_dir_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a"), b"hello").unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub").join("b"), b"world").unwrap();
+
+        let archive: Vec<u8> = stream_dir_archive(dir.path().to_path_buf())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap()
+            .concat();
+
+        let mut paths = tar::Archive::new(archive.as_slice())
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().into_owned())
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                Path::new("."),
+                Path::new("a"),
+                Path::new("sub"),
+                Path::new("sub/b")
+            ]
+        );
+
+        let missing = dir.path().join("missing");
+        assert!(
+            stream_dir_archive(missing)
+                .try_collect::<Vec<_>>()
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn extracted_files() {
+        let archive = build_archive([("out.txt", &b"hello"[..], 0o644)]).unwrap();
+        assert_eq!(extract_file(&archive, "/out.txt").unwrap(), b"hello");
+
+        let mut tar = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        tar.append_data(&mut header, "out", std::io::empty())
+            .unwrap();
+        let archive = tar.into_inner().unwrap();
+        assert!(extract_file(&archive, "/out").is_err());
+
+        assert!(extract_file(&[0; 1024], "/out").is_err());
+    }
+
+    #[tokio::test]
+    async fn extracted_files_to() {
+        let long = format!("{}/out.txt", "d".repeat(120));
+        let mut tar = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, &long, &b"hello"[..]).unwrap();
+        let archive = tar.into_inner().unwrap();
+
+        let mut contents = Vec::new();
+        extract_file_to(&archive[..], &long, &mut contents)
+            .await
+            .unwrap();
+        assert_eq!(contents, b"hello");
+
+        let archive = build_archive([("out.txt", &b"hello"[..], 0o644)]).unwrap();
+        let mut contents = Vec::new();
+        extract_file_to(&archive[..], "/out.txt", &mut contents)
+            .await
+            .unwrap();
+        assert_eq!(contents, b"hello");
+
+        let truncated = &archive[..HEADER_LEN + 2];
+        assert!(
+            extract_file_to(truncated, "/out.txt", &mut Vec::new())
+                .await
+                .is_err()
+        );
+
+        let mut tar = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        tar.append_data(&mut header, "out", std::io::empty())
+            .unwrap();
+        let archive = tar.into_inner().unwrap();
+        assert!(
+            extract_file_to(&archive[..], "/out", &mut Vec::new())
+                .await
+                .is_err()
+        );
+
+        assert!(
+            extract_file_to(&[0; 1024][..], "/out", &mut Vec::new())
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn deterministic_archives() {
+        let first =
+            build_archive([("/b.txt", &b"b"[..], 0o644), ("/a.txt", &b"a"[..], 0o755)]).unwrap();
+        let second =
+            build_archive([("a.txt", &b"a"[..], 0o755), ("b.txt", &b"b"[..], 0o644)]).unwrap();
+        assert_eq!(first, second);
+
+        let mut archive = tar::Archive::new(&first[..]);
+        let entries: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let header = entry.header();
+                assert_eq!(header.mtime().unwrap(), 0);
+                assert_eq!(header.uid().unwrap(), 0);
+                assert_eq!(header.gid().unwrap(), 0);
+                entry.path().unwrap().into_owned()
+            })
+            .collect();
+        assert_eq!(entries, [Path::new("a.txt"), Path::new("b.txt")]);
+    }
+
+    #[test]
+    fn content_hashes() {
+        let file = |path: &str, contents: &[u8], mode| (path.to_string(), contents.to_vec(), mode);
+
+        let hash =
+            content_hash(&[file("/a.txt", b"a", 0o644), file("/b/c.txt", b"c", 0o755)]).unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(
+            content_hash(&[file("b/c.txt", b"c", 0o755), file("a.txt", b"a", 0o644)]).unwrap(),
+            hash
+        );
+        assert_ne!(
+            content_hash(&[file("/a.txt", b"a", 0o644), file("/b/c.txt", b"c", 0o644)]).unwrap(),
+            hash
+        );
+        assert_ne!(
+            content_hash(&[file("/a.txt", b"a", 0o644), file("/b/c.txt", b"d", 0o755)]).unwrap(),
+            hash
+        );
+        assert!(content_hash(&[file("/../a.txt", b"a", 0o644)]).is_err());
+    }
+
+    #[test]
+    fn sha256_verification() {
+        let digest = "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824";
+        assert!(verify_sha256("/out", b"hello", digest).is_ok());
+
+        match verify_sha256("/out", b"hell", digest) {
+            Err(Error::ChecksumMismatch {
+                expected, actual, ..
+            }) => {
+                assert_eq!(expected, digest.to_ascii_lowercase());
+                assert_ne!(actual, expected);
+            }
+            result => panic!("unexpected result: {result:?}"),
+        }
+    }
+
+    #[test]
+    fn executable_entries() {
+        let header = |entry_type, mode| {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(entry_type);
+            header.set_mode(mode);
+            header.as_bytes().to_vec()
+        };
+
+        assert!(is_executable_entry(&header(tar::EntryType::Regular, 0o755)));
+        assert!(!is_executable_entry(&header(
+            tar::EntryType::Regular,
+            0o644
+        )));
+        assert!(is_executable_entry(&header(tar::EntryType::Symlink, 0o777)));
+        assert!(!is_executable_entry(&header(
+            tar::EntryType::Directory,
+            0o755
+        )));
+    }
+
+    #[test]
+    fn entry_paths() {
+        assert_eq!(entry_path("/etc/app.conf").unwrap(), "etc/app.conf");
+        assert_eq!(entry_path("//etc/app.conf").unwrap(), "etc/app.conf");
+        assert_eq!(entry_path("etc/app.conf").unwrap(), "etc/app.conf");
+        assert!(entry_path("/").is_err());
+        assert!(entry_path("").is_err());
+        assert!(entry_path("/etc/../app.conf").is_err());
+    }
+
+    #[test]
+    fn temp_siblings() {
+        assert_eq!(
+            temp_sibling("/etc/app.conf", "abc").unwrap(),
+            "/etc/.app.conf.abc.tmp"
+        );
+        assert_eq!(
+            temp_sibling("etc/app.conf", "abc").unwrap(),
+            "/etc/.app.conf.abc.tmp"
+        );
+        assert_eq!(
+            temp_sibling("/app.conf", "abc").unwrap(),
+            "/.app.conf.abc.tmp"
+        );
+        assert!(temp_sibling("/etc/", "abc").is_err());
+        assert!(temp_sibling("/", "abc").is_err());
+    }
 }
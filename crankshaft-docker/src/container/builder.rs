@@ -1,15 +1,67 @@
 //! Builders for containers.
 
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
 use bollard::Docker;
 use bollard::models::ContainerCreateBody;
 use bollard::query_parameters::CreateContainerOptions;
+use bollard::query_parameters::RemoveContainerOptions;
+use bollard::secret::EndpointSettings;
 use bollard::secret::HostConfig;
+use bollard::secret::HostConfigCgroupnsModeEnum;
+use bollard::secret::ImageInspect;
+use bollard::secret::Mount;
+use bollard::secret::MountTypeEnum;
+use bollard::secret::NetworkConnectRequest;
+use bollard::secret::NetworkingConfig;
+use bollard::secret::PortBinding;
+use bollard::secret::RestartPolicy;
+use bollard::secret::ThrottleDevice;
 use indexmap::IndexMap;
+use tracing::debug;
+use tracing::trace;
 use tracing::warn;
 
 use crate::Container;
 use crate::Error;
 use crate::Result;
+use crate::container::LifecycleObserver;
+use crate::container::TmpfsOptions;
+use crate::container::build_archive;
+use crate::has_security_option;
+use crate::raw::DaemonAddress;
+
+/// The SELinux relabeling of a bind mount added with [`Builder::bind()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeLinuxRelabel {
+    /// The content is labeled so that it can be shared between containers
+    /// (the `z` option).
+    Shared,
+
+    /// The content is labeled so that only this container can use it (the
+    /// `Z` option).
+    Private,
+}
+
+impl SeLinuxRelabel {
+    /// Gets the bind mount option of the relabeling.
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Shared => "z",
+            Self::Private => "Z",
+        }
+    }
+}
+
+/// The paths that [`Builder::read_only_rootfs_with_writable()`] always mounts
+/// tmpfs at, as most programs expect to be able to write to them.
+const DEFAULT_WRITABLE_PATHS: &[&str] = &["/tmp", "/run"];
+
+/// The directory of the timezone database.
+const ZONEINFO_DIR: &str = "/usr/share/zoneinfo";
 
 /// A builder for a [`Container`].
 pub struct Builder {
@@ -20,6 +72,9 @@ pub struct Builder {
     /// The image (e.g., `ubuntu:latest`).
     image: Option<String>,
 
+    /// The digest the image is pinned to (e.g., `sha256:...`).
+    image_digest: Option<String>,
+
     /// The program to run.
     program: Option<String>,
 
@@ -32,14 +87,60 @@ pub struct Builder {
     /// Whether or not the standard error is attached.
     attach_stderr: bool,
 
+    /// Whether or not the standard input is attached.
+    attach_stdin: bool,
+
+    /// Whether or not a TTY is allocated.
+    tty: bool,
+
     /// Environment variables.
     env: IndexMap<String, String>,
 
+    /// Environment variables whose values are secret.
+    secret_env: IndexMap<String, String>,
+
     /// The working directory.
     work_dir: Option<String>,
 
+    /// Labels.
+    labels: IndexMap<String, String>,
+
+    /// The exposed ports (e.g., `80/tcp`).
+    exposed_ports: Vec<String>,
+
+    /// The networks to connect the container to and the aliases of the
+    /// container on each.
+    networks: IndexMap<String, Vec<String>>,
+
+    /// The time to wait for the container to stop before killing it.
+    stop_timeout: Option<Duration>,
+
     /// Host configuration.
     host_config: Option<HostConfig>,
+
+    /// The files to upload to the container once it has been created.
+    files: Vec<(String, Vec<u8>, u32)>,
+
+    /// Whether or not an existing container with the same name is removed.
+    replace_existing: bool,
+
+    /// The observer of the container's lifecycle events.
+    observer: Option<Arc<dyn LifecycleObserver>>,
+
+    /// Raw JSON merged into the container configuration.
+    raw_config: Option<serde_json::Value>,
+
+    /// Raw JSON merged into the host configuration.
+    raw_host_config: Option<serde_json::Value>,
+
+    /// The timezone of the container (e.g., `Europe/Berlin`).
+    timezone: Option<String>,
+
+    /// The MAC address of the container (e.g., `02:42:ac:11:00:02`).
+    mac_address: Option<String>,
+
+    /// The address of the Docker daemon, if it is known.
+    address: Option<DaemonAddress>,
 }
 
 impl Builder {
@@ -48,22 +149,61 @@ impl Builder {
         Self {
             client,
             image: Default::default(),
+            image_digest: Default::default(),
             program: Default::default(),
             args: Default::default(),
             attach_stdout: false,
             attach_stderr: false,
+            attach_stdin: false,
+            tty: false,
             env: Default::default(),
+            secret_env: Default::default(),
             work_dir: Default::default(),
+            labels: Default::default(),
+            exposed_ports: Default::default(),
+            networks: Default::default(),
+            stop_timeout: Default::default(),
             host_config: Default::default(),
+            files: Default::default(),
+            replace_existing: false,
+            observer: None,
+            raw_config: None,
+            raw_host_config: None,
+            timezone: None,
+            mac_address: None,
+            address: None,
         }
     }
 
+    /// Sets the address of the Docker daemon, which is required to checkpoint
+    /// and restore the built container.
+    pub(crate) fn daemon_address(mut self, address: Option<DaemonAddress>) -> Self {
+        self.address = address;
+        self
+    }
+
     /// Adds an image name.
     pub fn image(mut self, image: impl Into<String>) -> Self {
         self.image = Some(image.into());
         self
     }
 
+    /// Pins the image to a digest (e.g., `sha256:...`).
+    ///
+    /// The container is created from the image referenced by the digest
+    /// (e.g., `ubuntu@sha256:...` for an image of `ubuntu`), replacing any
+    /// digest already in the image, so a tag such as `latest` that is moved to
+    /// another image cannot change what is run. Once the container has been
+    /// created, the image it uses is checked against the digest: if it does not
+    /// match, the container is removed and [`Self::try_build()`] fails with
+    /// [`Error::ImageDigestMismatch`].
+    ///
+    /// The digest must be a SHA-256 digest of 64 lowercase hexadecimal digits.
+    pub fn image_digest(mut self, digest: impl Into<String>) -> Self {
+        self.image_digest = Some(digest.into());
+        self
+    }
+
     /// Sets the program to run.
     pub fn program(mut self, program: impl Into<String>) -> Self {
         self.program = Some(program.into());
@@ -94,9 +234,43 @@ impl Builder {
         self
     }
 
+    /// Sets stdin to be attached.
+    ///
+    /// This also keeps the container's standard input open so it can be written
+    /// to with [`Container::attach_interactive()`].
+    pub fn attach_stdin(mut self) -> Self {
+        self.attach_stdin = true;
+        self
+    }
+
+    /// Allocates a TTY for the container.
+    ///
+    /// With a TTY, standard output and standard error are combined into a
+    /// single raw stream.
+    pub fn tty(mut self) -> Self {
+        self.tty = true;
+        self
+    }
+
     /// Sets an environment variable.
     pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
-        self.env.insert(name.into(), value.into());
+        let name = name.into();
+        self.secret_env.shift_remove(&name);
+        self.env.insert(name, value.into());
+        self
+    }
+
+    /// Sets an environment variable whose value is secret (e.g., a token).
+    ///
+    /// The value is only sent to the Docker daemon when the container is
+    /// created: it is replaced with `<redacted>` in the configuration returned
+    /// by [`Self::to_config()`] and is never logged by this crate. Note that,
+    /// as with any environment variable, the value is still visible to every
+    /// process within the container and to anyone who can inspect it.
+    pub fn secret_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let name = name.into();
+        self.env.shift_remove(&name);
+        self.secret_env.insert(name, value.into());
         self
     }
 
@@ -105,75 +279,1897 @@ impl Builder {
         mut self,
         variables: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
     ) -> Self {
-        self.env
-            .extend(variables.into_iter().map(|(k, v)| (k.into(), v.into())));
+        for (name, value) in variables {
+            self = self.env(name, value);
+        }
+
+        self
+    }
+
+    /// Sets environment variables to the values they have on the host.
+    ///
+    /// Like `docker run -e NAME`, each named variable is read from the
+    /// environment of the current process; variables that are not set (or are
+    /// not valid Unicode) are skipped.
+    ///
+    /// Take care with which variables are passed through: the host
+    /// environment often holds credentials and tokens, and anything set in the
+    /// container is visible to every process within it and to anyone who can
+    /// inspect the container.
+    pub fn env_passthrough(mut self, names: &[&str]) -> Self {
+        for name in names {
+            match std::env::var(name) {
+                Ok(value) => self = self.env(*name, value),
+                Err(_) => trace!("not passing through unset environment variable `{name}`"),
+            }
+        }
+
         self
     }
 
+    /// Sets the timezone of the container (e.g., `Europe/Berlin`).
+    ///
+    /// Containers use UTC unless configured otherwise. This sets the `TZ`
+    /// environment variable, which is enough for programs that read timezone
+    /// data from the image; if `mount_zoneinfo` is set, the host's zoneinfo
+    /// file for the timezone is also bind mounted read-only to
+    /// `/etc/localtime`, for images without timezone data and programs that
+    /// only read `/etc/localtime`.
+    ///
+    /// If the timezone database exists on this host (at
+    /// `/usr/share/zoneinfo`), the timezone must be in it; otherwise,
+    /// [`Self::try_build()`] fails with [`Error::InvalidBuilderField`]. Note
+    /// that the zoneinfo file is mounted from the host of the Docker daemon.
+    pub fn timezone(self, timezone: &str, mount_zoneinfo: bool) -> Self {
+        let mut builder = self.env("TZ", timezone);
+        builder.timezone = Some(timezone.to_string());
+
+        if mount_zoneinfo {
+            builder = builder.bind(
+                format!("{ZONEINFO_DIR}/{timezone}"),
+                "/etc/localtime",
+                true,
+                None,
+            );
+        }
+
+        builder
+    }
+
     /// Sets the working directory.
     pub fn work_dir(mut self, work_dir: impl Into<String>) -> Self {
         self.work_dir = Some(work_dir.into());
         self
     }
 
+    /// Sets a label.
+    ///
+    /// Labels can be used to find containers later, such as with
+    /// [`Docker::reap_by_label()`](crate::Docker::reap_by_label).
+    pub fn label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets multiple labels.
+    pub fn labels(
+        mut self,
+        labels: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
+    ) -> Self {
+        self.labels
+            .extend(labels.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Bind mounts a path on the host into the container.
+    ///
+    /// On hosts enforcing SELinux, the content of a bind mount is inaccessible
+    /// to the container unless it is labeled for use by containers; `relabel`
+    /// has the Docker daemon relabel it when the container is created (the `z`
+    /// or `Z` option). Relabeling has no effect if SELinux is not enabled on
+    /// the Docker daemon, which is logged as a warning when the container is
+    /// built.
+    pub fn bind(
+        mut self,
+        source: impl Into<String>,
+        target: impl Into<String>,
+        read_only: bool,
+        relabel: Option<SeLinuxRelabel>,
+    ) -> Self {
+        let mut bind = format!("{}:{}", source.into(), target.into());
+        let options: Vec<_> = [
+            read_only.then_some("ro"),
+            relabel.map(SeLinuxRelabel::as_str),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        if !options.is_empty() {
+            bind.push(':');
+            bind.push_str(&options.join(","));
+        }
+
+        self.host_config_mut()
+            .binds
+            .get_or_insert_with(Default::default)
+            .push(bind);
+        self
+    }
+
+    /// Adds a mount to the container.
+    ///
+    /// Unlike [`Self::bind()`], this supports all of the options of a mount,
+    /// such as the propagation of a bind mount (e.g., `rshared` or `rslave`,
+    /// set with [`MountBindOptions::propagation`]), its consistency on macOS
+    /// (e.g., `cached` or `delegated`, set with [`Mount::consistency`]), and
+    /// the options of volume and tmpfs mounts.
+    ///
+    /// [`MountBindOptions::propagation`]: bollard::secret::MountBindOptions::propagation
+    pub fn mount(mut self, mount: Mount) -> Self {
+        self.host_config_mut()
+            .mounts
+            .get_or_insert_with(Default::default)
+            .push(mount);
+        self
+    }
+
+    /// Mounts a tmpfs at a path within the container.
+    ///
+    /// The mount's size, mode, and flags such as `noexec` and `nosuid` are set
+    /// with [`TmpfsOptions`]. Mount flags require Docker API version 1.46 or
+    /// later; older daemons ignore them.
+    pub fn tmpfs(self, target: impl Into<String>, options: TmpfsOptions) -> Self {
+        self.mount(Mount {
+            target: Some(target.into()),
+            typ: Some(MountTypeEnum::TMPFS),
+            tmpfs_options: Some(options.into()),
+            ..Default::default()
+        })
+    }
+
+    /// Sets whether or not the container's root filesystem is mounted
+    /// read-only.
+    ///
+    /// Only the container's mounts are then writable; see
+    /// [`Self::read_only_rootfs_with_writable()`] to make common paths
+    /// writable as well.
+    pub fn read_only_rootfs(mut self, read_only: bool) -> Self {
+        self.host_config_mut().readonly_rootfs = Some(read_only);
+        self
+    }
+
+    /// Mounts the container's root filesystem read-only, with tmpfs mounted at
+    /// `/tmp`, `/run`, and each of `paths` so that they remain writable.
+    ///
+    /// The tmpfs mounts use the Docker daemon's default options (see
+    /// [`TmpfsOptions`]); use [`Self::tmpfs()`] instead for paths that need
+    /// other options. Paths are only mounted once, even if they are given
+    /// more than once or are one of the defaults. As with any tmpfs, the
+    /// contents of the writable paths are held in memory and are lost when
+    /// the container stops.
+    pub fn read_only_rootfs_with_writable(
+        self,
+        paths: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        let mut targets: Vec<String> = DEFAULT_WRITABLE_PATHS
+            .iter()
+            .map(|path| path.to_string())
+            .collect();
+        for path in paths {
+            let path = path.into();
+            if !targets.contains(&path) {
+                targets.push(path);
+            }
+        }
+
+        targets
+            .into_iter()
+            .fold(self.read_only_rootfs(true), |builder, target| {
+                builder.tmpfs(target, TmpfsOptions::default())
+            })
+    }
+
+    /// Publishes a port of the container (e.g., `80/tcp`) on the host.
+    ///
+    /// If `host_port` is `None`, the Docker daemon chooses an available port.
+    pub fn publish_port(
+        mut self,
+        container_port: impl Into<String>,
+        host_port: Option<u16>,
+    ) -> Self {
+        let container_port = container_port.into();
+
+        self.host_config_mut()
+            .port_bindings
+            .get_or_insert_with(Default::default)
+            .entry(container_port.clone())
+            .or_default()
+            .get_or_insert_with(Default::default)
+            .push(PortBinding {
+                host_ip: None,
+                host_port: host_port.map(|port| port.to_string()),
+            });
+
+        if !self.exposed_ports.contains(&container_port) {
+            self.exposed_ports.push(container_port);
+        }
+
+        self
+    }
+
+    /// Connects the container to a network.
+    ///
+    /// This can be called multiple times to connect the container to multiple
+    /// networks. The container is created on the first network, as a create
+    /// request only allows one, and is connected to the others once it has
+    /// been created.
+    pub fn network(self, name: impl Into<String>) -> Self {
+        self.network_with_aliases(name, std::iter::empty::<String>())
+    }
+
+    /// Connects the container to a network with the given aliases on that
+    /// network.
+    ///
+    /// See [`Self::network()`] for more information.
+    pub fn network_with_aliases(
+        mut self,
+        name: impl Into<String>,
+        aliases: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.networks
+            .entry(name.into())
+            .or_default()
+            .extend(aliases.into_iter().map(Into::into));
+        self
+    }
+
+    /// Disables networking for the container, which only has a loopback
+    /// interface (the `none` network mode).
+    ///
+    /// This is suited to running untrusted code that must not reach the
+    /// network. Files can still be uploaded to and downloaded from the
+    /// container, and commands run within it, as these go through the Docker
+    /// daemon rather than the container's network. Any networks previously
+    /// given to [`Self::network()`] are removed; connecting the container to a
+    /// network afterwards enables networking again.
+    pub fn no_network(mut self) -> Self {
+        self.networks.clear();
+        self.host_config_mut().network_mode = Some(String::from("none"));
+        self
+    }
+
+    /// Sets the MAC address of the container (e.g., `02:42:ac:11:00:02`).
+    ///
+    /// The address is set on the network the container is created on: the
+    /// first network given to [`Self::network()`], or the Docker daemon's
+    /// default network if there is none. It must be six pairs of hexadecimal
+    /// digits separated by colons; otherwise, [`Self::try_build()`] fails
+    /// with [`Error::InvalidBuilderField`].
+    pub fn mac_address(mut self, mac_address: &str) -> Self {
+        self.mac_address = Some(mac_address.to_string());
+        self
+    }
+
     /// Sets the host configuration.
+    ///
+    /// This replaces any host configuration previously set by other builder
+    /// methods, so it should be called before them.
     pub fn host_config(mut self, host_config: HostConfig) -> Self {
         self.host_config = Some(host_config);
         self
     }
 
-    /// Consumes `self` and attempts to create a Docker container.
+    /// Adds a file to be written to the container once it has been created.
     ///
-    /// Note that the creation of a container does not start the container.
-    pub async fn try_build(self, name: impl AsRef<str>) -> Result<Container> {
-        let image = self
-            .image
-            .ok_or_else(|| Error::MissingBuilderField("image"))?;
-        let program = self
-            .program
-            .ok_or_else(|| Error::MissingBuilderField("program"))?;
+    /// The files are uploaded in a single archive after the container is
+    /// created and before [`Self::try_build()`] returns, so they are present
+    /// when the container is started. Paths are resolved against the root of
+    /// the container as described in [`Container::upload_file()`], and `mode`
+    /// gives the file's permissions (e.g., `0o644`).
+    pub fn write_file(
+        mut self,
+        path: impl Into<String>,
+        contents: impl Into<Vec<u8>>,
+        mode: u32,
+    ) -> Self {
+        self.files.push((path.into(), contents.into(), mode));
+        self
+    }
 
-        let mut cmd = Vec::with_capacity(1 + self.args.len());
-        cmd.push(program);
-        cmd.extend(self.args);
+    /// Sets the restart policy.
+    ///
+    /// When using the `on-failure` policy, the `maximum_retry_count` of the
+    /// policy limits the number of times the container is restarted. The
+    /// number of restarts so far can be read with
+    /// [`Container::restart_count()`].
+    pub fn restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.host_config_mut().restart_policy = Some(policy);
+        self
+    }
 
-        let response = self
-            .client
-            .create_container(
-                Some(CreateContainerOptions {
-                    name: Some(name.as_ref().into()),
-                    ..Default::default()
-                }),
-                ContainerCreateBody {
-                    // NOTE: even though the following fields are optional, I
-                    // want _this_ struct to require the explicit designation
-                    // one way or the other and not rely on the default.
-                    cmd: Some(cmd),
-                    image: Some(image),
-                    // Override the entrypoint to the default Docker entrypoint as we're providing
-                    // the full command
-                    entrypoint: Some(vec![String::new()]),
-                    attach_stdout: Some(self.attach_stdout),
-                    attach_stderr: Some(self.attach_stderr),
-                    // END NOTE
-                    working_dir: self.work_dir,
-                    host_config: self.host_config,
-                    env: Some(self.env.iter().map(|(k, v)| format!("{k}={v}")).collect()),
-                    ..Default::default()
-                },
+    /// Sets the time to wait for the container to exit after being sent its
+    /// stop signal before it is killed.
+    ///
+    /// The timeout is rounded down to the nearest second. By default, the
+    /// Docker daemon waits 10 seconds.
+    pub fn stop_timeout(mut self, timeout: Duration) -> Self {
+        self.stop_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets whether or not an init process is run as PID 1 in the container.
+    ///
+    /// The init process forwards signals to the container's program and reaps
+    /// zombie processes. If this is not set, the Docker daemon's default is
+    /// used.
+    ///
+    /// The init binary cannot be chosen per container: the Docker daemon uses
+    /// the binary set by its `init-path` option (`docker-init`, a build of
+    /// `tini`, by default). To use a custom init shipped within the image
+    /// instead, leave this unset and run the init as the program (for example,
+    /// `.program("/sbin/tini").args(["--", "my-program"])`).
+    pub fn init(mut self, init: bool) -> Self {
+        self.host_config_mut().init = Some(init);
+        self
+    }
+
+    /// Adds supplementary groups for the container's processes to belong to.
+    ///
+    /// Groups can be given by name (resolved within the container's image) or
+    /// by ID. This is useful for accessing bind mounted files or devices owned
+    /// by a group, such as `video` or the host's `docker` group (whose ID
+    /// usually differs between the host and the image, so it is best given by
+    /// ID).
+    pub fn group_add(mut self, groups: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.host_config_mut()
+            .group_add
+            .get_or_insert_with(Default::default)
+            .extend(groups.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the adjustment to the container's score for the kernel's OOM
+    /// killer.
+    ///
+    /// The adjustment must be within `-1000..=1000`; lower values make the
+    /// container's processes less likely to be killed when the host is out of
+    /// memory.
+    pub fn oom_score_adj(mut self, adj: i32) -> Self {
+        self.host_config_mut().oom_score_adj = Some(adj.into());
+        self
+    }
+
+    /// Sets the memory limit of the container in bytes.
+    ///
+    /// A container that exceeds its limit has its processes killed by the
+    /// kernel's OOM killer, unless it is disabled with
+    /// [`Self::oom_kill_disable()`]. A limit of `0` means no limit.
+    pub fn memory(mut self, bytes: i64) -> Self {
+        self.host_config_mut().memory = Some(bytes);
+        self
+    }
+
+    /// Sets whether or not the kernel's OOM killer is disabled for the
+    /// container.
+    ///
+    /// Without a memory limit, a container whose OOM killer is disabled can
+    /// exhaust the host's memory and hang it, so a memory limit must also be
+    /// set with [`Self::memory()`]; otherwise, [`Self::try_build()`] fails
+    /// with [`Error::InvalidBuilderField`].
+    pub fn oom_kill_disable(mut self, disable: bool) -> Self {
+        self.host_config_mut().oom_kill_disable = Some(disable);
+        self
+    }
+
+    /// Sets the parent cgroup of the container.
+    ///
+    /// If the path is not absolute, it is relative to the cgroup of the Docker
+    /// daemon's init process. The cgroup is created if it does not exist.
+    pub fn cgroup_parent(mut self, parent: impl Into<String>) -> Self {
+        self.host_config_mut().cgroup_parent = Some(parent.into());
+        self
+    }
+
+    /// Sets the cgroup namespace mode of the container.
+    ///
+    /// By default, the Docker daemon's configured mode is used.
+    pub fn cgroupns_mode(mut self, mode: HostConfigCgroupnsModeEnum) -> Self {
+        self.host_config_mut().cgroupns_mode = Some(mode);
+        self
+    }
+
+    /// Sets the CPUs the container is allowed to run on (e.g., `0-3,7`).
+    ///
+    /// The value is a comma-separated list of CPU numbers and inclusive
+    /// ranges.
+    pub fn cpuset_cpus(mut self, cpus: impl Into<String>) -> Self {
+        self.host_config_mut().cpuset_cpus = Some(cpus.into());
+        self
+    }
+
+    /// Sets the memory nodes the container is allowed to use (e.g., `0,1`).
+    ///
+    /// The value uses the same syntax as [`Self::cpuset_cpus()`] and is only
+    /// effective on NUMA systems.
+    pub fn cpuset_mems(mut self, mems: impl Into<String>) -> Self {
+        self.host_config_mut().cpuset_mems = Some(mems.into());
+        self
+    }
+
+    /// Sets the length of the container's CFS scheduler period in
+    /// microseconds.
+    ///
+    /// The period must be within `1000..=1000000` (1ms to 1s). Together with
+    /// [`Self::cpu_quota()`], it limits the container to `quota / period` CPUs
+    /// of runtime per period, which bounds scheduling latency more precisely
+    /// than a relative CPU weight.
+    pub fn cpu_period(mut self, period: i64) -> Self {
+        self.host_config_mut().cpu_period = Some(period);
+        self
+    }
+
+    /// Sets the CPU runtime, in microseconds, the container may use within
+    /// each CFS scheduler period.
+    ///
+    /// The quota must be at least `1000` (1ms), or `-1` for no limit; see
+    /// [`Self::cpu_period()`].
+    pub fn cpu_quota(mut self, quota: i64) -> Self {
+        self.host_config_mut().cpu_quota = Some(quota);
+        self
+    }
+
+    /// Sets the memory reservation of the container in bytes.
+    ///
+    /// The reservation is a soft limit: the container may use more memory
+    /// while the host has memory to spare, but is reclaimed down to the
+    /// reservation when the host is under memory pressure. It does not keep
+    /// the Docker daemon from starting containers whose reservations exceed
+    /// the host's memory, so it is best treated as a hint for schedulers
+    /// placing containers. If a memory limit is also set with
+    /// [`Self::memory()`], the reservation must not exceed it; otherwise,
+    /// [`Self::try_build()`] fails with [`Error::InvalidBuilderField`].
+    pub fn memory_reservation(mut self, bytes: i64) -> Self {
+        self.host_config_mut().memory_reservation = Some(bytes);
+        self
+    }
+
+    /// Sets the relative CPU weight of the container (the default is `1024`).
+    ///
+    /// Docker has no CPU reservation for containers; the weight is the
+    /// closest soft equivalent, as it only takes effect when CPUs are
+    /// contended, at which point CPU time is shared between containers in
+    /// proportion to their weights. The weight must be within `2..=262144`.
+    pub fn cpu_shares(mut self, shares: i64) -> Self {
+        self.host_config_mut().cpu_shares = Some(shares);
+        self
+    }
+
+    /// Sets the relative block I/O weight of the container.
+    ///
+    /// The weight must be within `10..=1000`.
+    pub fn blkio_weight(mut self, weight: u16) -> Self {
+        self.host_config_mut().blkio_weight = Some(weight);
+        self
+    }
+
+    /// Limits the rate of reads from a device (e.g., `/dev/sda`) in bytes per
+    /// second.
+    pub fn blkio_device_read_bps(mut self, path: impl Into<String>, rate: u64) -> Self {
+        push_throttle(
+            &mut self.host_config_mut().blkio_device_read_bps,
+            path.into(),
+            rate,
+        );
+        self
+    }
+
+    /// Limits the rate of writes to a device (e.g., `/dev/sda`) in bytes per
+    /// second.
+    pub fn blkio_device_write_bps(mut self, path: impl Into<String>, rate: u64) -> Self {
+        push_throttle(
+            &mut self.host_config_mut().blkio_device_write_bps,
+            path.into(),
+            rate,
+        );
+        self
+    }
+
+    /// Limits the rate of reads from a device (e.g., `/dev/sda`) in I/O
+    /// operations per second.
+    pub fn blkio_device_read_iops(mut self, path: impl Into<String>, rate: u64) -> Self {
+        push_throttle(
+            &mut self.host_config_mut().blkio_device_read_iops,
+            path.into(),
+            rate,
+        );
+        self
+    }
+
+    /// Limits the rate of writes to a device (e.g., `/dev/sda`) in I/O
+    /// operations per second.
+    pub fn blkio_device_write_iops(mut self, path: impl Into<String>, rate: u64) -> Self {
+        push_throttle(
+            &mut self.host_config_mut().blkio_device_write_iops,
+            path.into(),
+            rate,
+        );
+        self
+    }
+
+    /// Sets the OCI runtime the container is run with (e.g., `runsc` for
+    /// gVisor or `kata-runtime` for Kata Containers).
+    ///
+    /// Sandboxed runtimes such as these isolate untrusted workloads from the
+    /// host more strongly than the default runtime (`runc`). The runtime must
+    /// be registered with the Docker daemon (see the `runtimes` of its
+    /// configuration); otherwise, [`Self::try_build()`] fails with
+    /// [`Error::InvalidBuilderField`].
+    pub fn runtime(mut self, runtime: impl Into<String>) -> Self {
+        self.host_config_mut().runtime = Some(runtime.into());
+        self
+    }
+
+    /// Sets an annotation of the container.
+    ///
+    /// Annotations are arbitrary metadata that, unlike labels, are passed to
+    /// the OCI runtime when the container is started, which some runtimes use
+    /// for configuration.
+    pub fn annotation(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.host_config_mut()
+            .annotations
+            .get_or_insert_with(Default::default)
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets the seccomp profile of the container, which restricts the system
+    /// calls its processes can make.
+    ///
+    /// The profile is either the JSON of a custom profile, `unconfined` to run
+    /// the container without a profile (for example, when debugging), or
+    /// `builtin` for the Docker daemon's default profile. Profiles given as
+    /// JSON are checked to be valid JSON objects when the container is built;
+    /// otherwise, [`Self::try_build()`] fails with
+    /// [`Error::InvalidBuilderField`].
+    pub fn seccomp_profile(self, profile: impl Into<String>) -> Self {
+        self.security_opt(format!("seccomp={profile}", profile = profile.into()))
+    }
+
+    /// Sets the seccomp profile of the container to the contents of a JSON
+    /// file on the host.
+    ///
+    /// The file is read immediately, as the Docker daemon only accepts the
+    /// contents of the profile; see [`Self::seccomp_profile()`]. An error is
+    /// returned if the file cannot be read.
+    pub fn seccomp_profile_path(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let profile = std::fs::read_to_string(path).map_err(|e| {
+            Error::InvalidBuilderField(
+                "seccomp_profile",
+                format!("failed to read `{path}`: {e}", path = path.display()),
             )
-            .await
-            .map_err(Error::Docker)?;
+        })?;
 
-        for warning in &response.warnings {
-            warn!("{warning}");
-        }
+        Ok(self.seccomp_profile(profile))
+    }
 
-        Ok(Container {
-            client: self.client,
-            name: response.id,
-            attach_stdout: self.attach_stdout,
-            attach_stderr: self.attach_stderr,
-        })
+    /// Sets the AppArmor profile of the container (e.g., `docker-default`).
+    ///
+    /// The profile must already be loaded on the host; `unconfined` runs the
+    /// container without a profile.
+    pub fn apparmor_profile(self, profile: &str) -> Self {
+        self.security_opt(format!("apparmor={profile}"))
+    }
+
+    /// Adds an SELinux label option of the container (e.g.,
+    /// `type:svirt_apache_t` or `level:s0:c100,c200`).
+    ///
+    /// On hosts enforcing SELinux, bind mounts are only accessible to the
+    /// container if their label allows it; `disable` turns off labeling for
+    /// the container instead. This may be called more than once to set
+    /// several parts of the label.
+    pub fn security_label(self, label: &str) -> Self {
+        self.security_opt(format!("label={label}"))
+    }
+
+    /// Adds a security option of the container.
+    fn security_opt(mut self, opt: String) -> Self {
+        self.host_config_mut()
+            .security_opt
+            .get_or_insert_with(Default::default)
+            .push(opt);
+        self
+    }
+
+    /// Sets a namespaced kernel parameter of the container (e.g.,
+    /// `net.ipv4.ip_forward` to `1`).
+    ///
+    /// Only parameters that are namespaced by the kernel can be set, as they
+    /// affect the container alone; the Docker daemon rejects others, as well
+    /// as `net.*` parameters for containers using the host's network.
+    pub fn sysctl(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.host_config_mut()
+            .sysctls
+            .get_or_insert_with(Default::default)
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Allows processes in the container to bind ports below 1024 without
+    /// any capabilities.
+    ///
+    /// This sets the `net.ipv4.ip_unprivileged_port_start` parameter (see
+    /// [`Self::sysctl()`]) to `0`, which is often needed to run services on
+    /// their standard ports as a non-root user or under a rootless daemon.
+    ///
+    /// Any process in the container, including one running as an unprivileged
+    /// user, can then bind any port in the container's network namespace, so
+    /// a compromised process could impersonate a service such as SSH or DNS
+    /// to the container's peers. Ports on the host are not affected.
+    pub fn allow_privileged_ports(self) -> Self {
+        self.sysctl("net.ipv4.ip_unprivileged_port_start", "0")
+    }
+
+    /// Sets a storage driver option of the container (e.g., `size` to `10G`).
+    ///
+    /// The `size` option limits the size of the container's writable layer,
+    /// which caps how much it can write to disk outside of its mounts. The
+    /// options supported depend on the storage driver of the Docker daemon;
+    /// for example, `overlay2` only supports `size` when its backing
+    /// filesystem is XFS mounted with the `pquota` option. If the storage
+    /// driver does not support an option, [`Self::try_build()`] fails with
+    /// [`Error::InvalidBuilderField`].
+    pub fn storage_opt(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.host_config_mut()
+            .storage_opt
+            .get_or_insert_with(Default::default)
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets whether or not an existing container with the same name is force
+    /// removed before the container is created.
+    ///
+    /// By default, creating a container with the name of an existing
+    /// container fails with [`Error::NameConflict`].
+    pub fn replace_existing(mut self, replace: bool) -> Self {
+        self.replace_existing = replace;
+        self
+    }
+
+    /// Sets the observer of the container's lifecycle events.
+    ///
+    /// See [`LifecycleObserver`] for more information.
+    pub fn observer(mut self, observer: Arc<dyn LifecycleObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Deep merges raw JSON into the container configuration sent to the
+    /// Docker daemon.
+    ///
+    /// This is an escape hatch for options of the Docker Engine API that this
+    /// builder does not support. The value uses the field names of the API
+    /// (e.g., `{"StopSignal": "SIGINT"}`). Objects are merged recursively,
+    /// while any other value (including an array) replaces the existing value;
+    /// a `null` unsets the field. Calling this multiple times merges each
+    /// value in turn.
+    ///
+    /// Raw JSON is merged last, so it overrides options set by other builder
+    /// methods when they conflict. The merged configuration must still be
+    /// understood by the version of [`bollard`] in use: fields it does not
+    /// know are dropped with a warning, and values of the wrong type cause
+    /// [`Self::try_build()`] to fail.
+    pub fn raw_config(mut self, config: serde_json::Value) -> Self {
+        merge_json(
+            self.raw_config.get_or_insert(serde_json::Value::Null),
+            config,
+        );
+        self
+    }
+
+    /// Deep merges raw JSON into the host configuration sent to the Docker
+    /// daemon (e.g., `{"ShmSize": 67108864}`).
+    ///
+    /// This is merged before [`Self::raw_config()`], but otherwise behaves the
+    /// same way; see it for more information.
+    pub fn raw_host_config(mut self, host_config: serde_json::Value) -> Self {
+        merge_json(
+            self.raw_host_config.get_or_insert(serde_json::Value::Null),
+            host_config,
+        );
+        self
+    }
+
+    /// Gets a mutable reference to the host configuration, creating a default
+    /// one if it has not yet been set.
+    fn host_config_mut(&mut self) -> &mut HostConfig {
+        self.host_config.get_or_insert_with(Default::default)
+    }
+
+    /// Gets the configuration that would be sent to the Docker daemon to
+    /// create the container, without creating it.
+    ///
+    /// The host configuration is returned separately from the container
+    /// configuration (in which it is left unset). The builder is not
+    /// validated; required fields that have not been set are left unset in
+    /// the returned configuration, whereas [`Self::try_build()`] reports them
+    /// as errors.
+    ///
+    /// The values of environment variables set with [`Self::secret_env()`] are
+    /// redacted. Any JSON given to [`Self::raw_config()`] or [`Self::raw_host_config()`]
+    /// is not merged into the returned configuration.
+    pub fn to_config(&self) -> (ContainerCreateBody, Option<HostConfig>) {
+        let cmd = self
+            .program
+            .iter()
+            .chain(&self.args)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut config = ContainerCreateBody {
+            // NOTE: even though the following fields are optional, I
+            // want _this_ struct to require the explicit designation
+            // one way or the other and not rely on the default.
+            cmd: (!cmd.is_empty()).then_some(cmd),
+            image: self.image.as_deref().map(|image| match &self.image_digest {
+                Some(digest) => pin_image(image, digest),
+                None => image.to_string(),
+            }),
+            // Override the entrypoint to the default Docker entrypoint as we're providing
+            // the full command
+            entrypoint: Some(vec![String::new()]),
+            attach_stdout: Some(self.attach_stdout),
+            attach_stderr: Some(self.attach_stderr),
+            attach_stdin: Some(self.attach_stdin),
+            open_stdin: Some(self.attach_stdin),
+            tty: Some(self.tty),
+            // END NOTE
+            working_dir: self.work_dir.clone(),
+            stop_timeout: self
+                .stop_timeout
+                .map(|t| i64::try_from(t.as_secs()).unwrap_or(i64::MAX)),
+            env: Some(self.env_vars(true)),
+            labels: (!self.labels.is_empty()).then(|| {
+                self.labels
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect()
+            }),
+            exposed_ports: (!self.exposed_ports.is_empty()).then(|| {
+                self.exposed_ports
+                    .iter()
+                    .map(|port| (port.clone(), HashMap::new()))
+                    .collect()
+            }),
+            ..Default::default()
+        };
+
+        let mut host_config = self.host_config.clone();
+        if let Some((network, aliases)) = self.networks.first() {
+            config.networking_config = Some(NetworkingConfig {
+                endpoints_config: Some(HashMap::from([(
+                    network.clone(),
+                    EndpointSettings {
+                        mac_address: self.mac_address.clone(),
+                        ..endpoint_settings(aliases)
+                    },
+                )])),
+            });
+
+            host_config
+                .get_or_insert_with(Default::default)
+                .network_mode = Some(network.clone());
+        } else {
+            // Without an endpoint to set it on, the daemon moves the
+            // container-wide (deprecated) address to the default network
+            config.mac_address = self.mac_address.clone();
+        }
+
+        (config, host_config)
+    }
+
+    /// Gets the environment variables of the container in the form
+    /// `NAME=value`, optionally redacting the values of secret variables.
+    fn env_vars(&self, redact: bool) -> Vec<String> {
+        let secrets = self.secret_env.iter().map(|(k, v)| {
+            if redact {
+                format!("{k}=<redacted>")
+            } else {
+                format!("{k}={v}")
+            }
+        });
+
+        self.env
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .chain(secrets)
+            .collect()
+    }
+
+    /// Gets the body of the request to create the container, with any raw
+    /// JSON merged into it.
+    fn create_body(&self) -> Result<ContainerCreateBody> {
+        let (mut body, host_config) = self.to_config();
+        body.host_config = host_config;
+        body.env = Some(self.env_vars(false));
+
+        if self.raw_config.is_none() && self.raw_host_config.is_none() {
+            return Ok(body);
+        }
+
+        let invalid = |e: serde_json::Error| {
+            Error::InvalidBuilderField("raw_config", format!("invalid raw JSON: {e}"))
+        };
+
+        let mut value = serde_json::to_value(&body).map_err(invalid)?;
+        if let Some(raw) = &self.raw_host_config {
+            let serde_json::Value::Object(map) = &mut value else {
+                unreachable!("container configuration should serialize to an object");
+            };
+
+            merge_json(
+                map.entry("HostConfig").or_insert(serde_json::Value::Null),
+                raw.clone(),
+            );
+        }
+
+        if let Some(raw) = &self.raw_config {
+            merge_json(&mut value, raw.clone());
+        }
+
+        let body: ContainerCreateBody = serde_json::from_value(value.clone()).map_err(invalid)?;
+
+        let mut dropped = Vec::new();
+        dropped_fields(
+            &value,
+            &serde_json::to_value(&body).map_err(invalid)?,
+            "",
+            &mut dropped,
+        );
+        for field in dropped {
+            warn!("ignoring raw container configuration field `{field}` unknown to bollard");
+        }
+
+        Ok(body)
+    }
+
+    /// Consumes `self` and attempts to create a Docker container.
+    ///
+    /// If `name` is empty, the Docker daemon generates a unique name. For
+    /// ephemeral containers that should still be recognizable by name, use
+    /// [`sanitize_name()`](crate::container::sanitize_name) to create a valid,
+    /// unique name from a prefix, which avoids conflicts with existing
+    /// containers.
+    ///
+    /// Note that the creation of a container does not start the container.
+    pub async fn try_build(self, name: impl AsRef<str>) -> Result<Container> {
+        if self.image.is_none() {
+            return Err(Error::MissingBuilderField("image"));
+        }
+
+        if self.program.is_none() {
+            return Err(Error::MissingBuilderField("program"));
+        }
+
+        if let Some(digest) = &self.image_digest {
+            validate_digest(digest)?;
+        }
+
+        if let Some(timezone) = &self.timezone {
+            validate_timezone(timezone, Path::new(ZONEINFO_DIR))?;
+        }
+
+        if let Some(mac_address) = &self.mac_address {
+            validate_mac_address(mac_address)?;
+        }
+
+        let body = self.create_body()?;
+        if let Some(host_config) = &body.host_config {
+            validate_host_config(host_config)?;
+
+            if relabels_binds(host_config) {
+                warn_without_selinux(&self.client).await;
+            }
+        }
+
+        // Build the archive of files up front so that invalid paths are reported
+        // before the container is created
+        let archive = if self.files.is_empty() {
+            None
+        } else {
+            Some(build_archive(self.files.iter().map(
+                |(path, contents, mode)| (path.as_str(), contents.as_slice(), *mode),
+            ))?)
+        };
+
+        let name = name.as_ref();
+        let options = CreateContainerOptions {
+            name: Some(name.into()),
+            ..Default::default()
+        };
+
+        let response = match self
+            .client
+            .create_container(Some(options.clone()), body.clone())
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => match Error::from(e) {
+                Error::Conflict(_) if self.replace_existing => {
+                    debug!("removing existing container `{name}` to replace it");
+                    self.client
+                        .remove_container(
+                            name,
+                            Some(RemoveContainerOptions {
+                                force: true,
+                                ..Default::default()
+                            }),
+                        )
+                        .await
+                        .map_err(Error::from)?;
+
+                    self.client
+                        .create_container(Some(options), body)
+                        .await
+                        .map_err(|e| create_error(name, e))?
+                }
+                Error::Conflict(_) => return Err(conflict(name)),
+                e => return Err(rejected_field_error(e)),
+            },
+        };
+
+        for warning in &response.warnings {
+            warn!("{warning}");
+        }
+
+        let container = Container {
+            client: self.client,
+            name: response.id,
+            attach_stdout: self.attach_stdout,
+            attach_stderr: self.attach_stderr,
+            observer: self.observer,
+            address: self.address,
+        };
+        container.observe(|observer, name, at| observer.created(name, at));
+
+        if let Some(digest) = &self.image_digest {
+            if let Err(e) = verify_digest(&container, digest).await {
+                // Don't leak a container running an unexpected image
+                if let Err(e) = container.force_remove().await {
+                    warn!("failed to remove container after failing to verify its image: {e}");
+                }
+
+                return Err(e);
+            }
+        }
+
+        // The container was created on the first network
+        for (network, aliases) in self.networks.iter().skip(1) {
+            debug!(
+                "connecting container `{name}` to network `{network}`",
+                name = container.name
+            );
+
+            let result = container
+                .client
+                .connect_network(
+                    network,
+                    NetworkConnectRequest {
+                        container: Some(container.name.clone()),
+                        endpoint_config: Some(endpoint_settings(aliases)),
+                    },
+                )
+                .await;
+
+            if let Err(e) = result {
+                // Don't leak the container if it could not be connected
+                if let Err(e) = container.force_remove().await {
+                    warn!(
+                        "failed to remove container after failing to connect it to a network: {e}"
+                    );
+                }
+
+                return Err(e.into());
+            }
+        }
+
+        if let Some(archive) = archive {
+            if let Err(e) = container.upload_archive("/", archive).await {
+                // Don't leak the container if the files could not be written
+                if let Err(e) = container.force_remove().await {
+                    warn!("failed to remove container after failing to write its files: {e}");
+                }
+
+                return Err(e);
+            }
+        }
+
+        Ok(container)
+    }
+}
+
+/// Creates the settings of a network endpoint with the given aliases.
+pub(super) fn endpoint_settings(aliases: &[String]) -> EndpointSettings {
+    EndpointSettings {
+        aliases: (!aliases.is_empty()).then(|| aliases.to_vec()),
+        ..Default::default()
+    }
+}
+
+/// Deep merges `patch` into `target`.
+///
+/// Objects are merged recursively; any other value in `patch` replaces the
+/// value in `target`.
+fn merge_json(target: &mut serde_json::Value, patch: serde_json::Value) {
+    match (target, patch) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge_json(target.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+/// Collects the paths of the non-null fields of `expected` that are missing
+/// from `actual`.
+fn dropped_fields(
+    expected: &serde_json::Value,
+    actual: &serde_json::Value,
+    prefix: &str,
+    dropped: &mut Vec<String>,
+) {
+    let serde_json::Value::Object(expected) = expected else {
+        return;
+    };
+
+    for (key, value) in expected {
+        if value.is_null() {
+            continue;
+        }
+
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        match actual.get(key) {
+            Some(actual) => dropped_fields(value, actual, &path, dropped),
+            None => dropped.push(path),
+        }
+    }
+}
+
+/// Pins an image reference to a digest, replacing any digest it already has.
+fn pin_image(image: &str, digest: &str) -> String {
+    let name = image.split_once('@').map_or(image, |(name, _)| name);
+    format!("{name}@{digest}")
+}
+
+/// Validates a digest that an image is pinned to.
+fn validate_digest(digest: &str) -> Result<()> {
+    let valid = digest.strip_prefix("sha256:").is_some_and(|hex| {
+        hex.len() == 64 && hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    });
+
+    if !valid {
+        return Err(Error::InvalidBuilderField(
+            "image_digest",
+            format!("`{digest}` is not a SHA-256 digest of the form `sha256:<64 hex digits>`"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Verifies that a created container uses the image with a digest.
+async fn verify_digest(container: &Container, digest: &str) -> Result<()> {
+    let image = container.inspect().await?.image.unwrap_or_default();
+    let inspect = container
+        .client
+        .inspect_image(&image)
+        .await
+        .map_err(Error::from)?;
+
+    if digest_matches(&inspect, digest) {
+        return Ok(());
+    }
+
+    Err(Error::ImageDigestMismatch {
+        image,
+        expected: digest.to_string(),
+    })
+}
+
+/// Checks whether an image has a digest, either as its ID or as the digest of
+/// one of its repository references (e.g., `ubuntu@sha256:...`).
+fn digest_matches(image: &ImageInspect, digest: &str) -> bool {
+    image.id.as_deref() == Some(digest)
+        || image
+            .repo_digests
+            .iter()
+            .flatten()
+            .any(|reference| reference.split_once('@').is_some_and(|(_, d)| d == digest))
+}
+
+/// Adds a device rate limit to a list of limits.
+fn push_throttle(devices: &mut Option<Vec<ThrottleDevice>>, path: String, rate: u64) {
+    devices
+        .get_or_insert_with(Default::default)
+        .push(ThrottleDevice {
+            path: Some(path),
+            rate: Some(i64::try_from(rate).unwrap_or(i64::MAX)),
+        });
+}
+
+/// Converts an error from creating a container into an [`Error`].
+fn create_error(name: &str, e: bollard::errors::Error) -> Error {
+    match Error::from(e) {
+        Error::Conflict(_) => conflict(name),
+        e => rejected_field_error(e),
+    }
+}
+
+/// The errors of the Docker daemon rejecting a builder field when creating a
+/// container, as a substring identifying the error message, the field, and an
+/// explanation of the error.
+const REJECTED_FIELDS: &[(&str, &str, &str)] = &[
+    (
+        "storage-opt",
+        "storage_opt",
+        "the storage driver of the Docker daemon does not support the options",
+    ),
+    (
+        "storage opt",
+        "storage_opt",
+        "the storage driver of the Docker daemon does not support the options",
+    ),
+    (
+        "unknown or invalid runtime name",
+        "runtime",
+        "the runtime is not registered with the Docker daemon",
+    ),
+];
+
+/// Converts an error from the Docker daemon rejecting a builder field (such as
+/// unsupported storage options or an unknown runtime) into an error for the
+/// field; other errors are returned unchanged.
+fn rejected_field_error(e: Error) -> Error {
+    let message = match e.docker_error() {
+        Some(bollard::errors::Error::DockerResponseServerError { message, .. }) => message,
+        _ => return e,
+    };
+
+    let lower = message.to_lowercase();
+    match REJECTED_FIELDS
+        .iter()
+        .find(|(needle, ..)| lower.contains(needle))
+    {
+        Some((_, field, explanation)) => {
+            Error::InvalidBuilderField(field, format!("{explanation}: {message}"))
+        }
+        None => e,
+    }
+}
+
+/// Creates the error for a container name that is already in use.
+fn conflict(name: &str) -> Error {
+    Error::NameConflict {
+        name: name.to_string(),
+    }
+}
+
+/// Validates a host configuration before it is sent to the Docker daemon.
+fn validate_host_config(host_config: &HostConfig) -> Result<()> {
+    if let Some(adj) = host_config.oom_score_adj {
+        if !(-1000..=1000).contains(&adj) {
+            return Err(Error::InvalidBuilderField(
+                "oom_score_adj",
+                format!("{adj} is not within the range -1000 to 1000"),
+            ));
+        }
+    }
+
+    if host_config.oom_kill_disable == Some(true)
+        && host_config.memory.is_none_or(|memory| memory <= 0)
+    {
+        return Err(Error::InvalidBuilderField(
+            "oom_kill_disable",
+            String::from("the OOM killer cannot be disabled without a memory limit"),
+        ));
+    }
+
+    if let (Some(reservation), Some(limit)) = (host_config.memory_reservation, host_config.memory) {
+        if limit > 0 && reservation > limit {
+            return Err(Error::InvalidBuilderField(
+                "memory_reservation",
+                format!("{reservation} exceeds the memory limit of {limit}"),
+            ));
+        }
+    }
+
+    if let Some(shares) = host_config.cpu_shares {
+        // Docker treats a weight of zero as unset
+        if shares != 0 && !(2..=262_144).contains(&shares) {
+            return Err(Error::InvalidBuilderField(
+                "cpu_shares",
+                format!("{shares} is not within the range 2 to 262144"),
+            ));
+        }
+    }
+
+    if let Some(weight) = host_config.blkio_weight {
+        if !(10..=1000).contains(&weight) {
+            return Err(Error::InvalidBuilderField(
+                "blkio_weight",
+                format!("{weight} is not within the range 10 to 1000"),
+            ));
+        }
+    }
+
+    if let Some(period) = host_config.cpu_period {
+        if !(1000..=1_000_000).contains(&period) {
+            return Err(Error::InvalidBuilderField(
+                "cpu_period",
+                format!("{period} is not within the range 1000 to 1000000"),
+            ));
+        }
+    }
+
+    if let Some(quota) = host_config.cpu_quota {
+        // Docker treats `0` as unset and `-1` as unlimited
+        if (1..1000).contains(&quota) {
+            return Err(Error::InvalidBuilderField(
+                "cpu_quota",
+                format!("{quota} is less than 1000"),
+            ));
+        }
+    }
+
+    for opt in host_config.security_opt.iter().flatten() {
+        if let Some(profile) = opt.strip_prefix("seccomp=") {
+            validate_seccomp_profile(profile)?;
+        }
+    }
+
+    for mount in host_config.mounts.iter().flatten() {
+        if mount.target.as_deref().is_none_or(str::is_empty) {
+            return Err(Error::InvalidBuilderField(
+                "mount",
+                String::from("mount has no target"),
+            ));
+        }
+    }
+
+    if let Some(cpus) = &host_config.cpuset_cpus {
+        validate_cpuset("cpuset_cpus", cpus)?;
+    }
+
+    if let Some(mems) = &host_config.cpuset_mems {
+        validate_cpuset("cpuset_mems", mems)?;
+    }
+
+    Ok(())
+}
+
+/// Checks whether any bind mount of a host configuration is relabeled for
+/// SELinux.
+fn relabels_binds(host_config: &HostConfig) -> bool {
+    host_config.binds.iter().flatten().any(|bind| {
+        bind.splitn(3, ':')
+            .nth(2)
+            .is_some_and(|options| options.split(',').any(|o| o == "z" || o == "Z"))
+    })
+}
+
+/// Warns that relabeling bind mounts has no effect if SELinux is not enabled
+/// on the Docker daemon.
+async fn warn_without_selinux(client: &Docker) {
+    match client.info().await {
+        Ok(info) => {
+            if !has_security_option(
+                info.security_options.as_deref().unwrap_or_default(),
+                "selinux",
+            ) {
+                warn!(
+                    "bind mounts are relabeled for SELinux, but SELinux is not enabled on the \
+                     Docker daemon"
+                );
+            }
+        }
+        Err(e) => debug!("failed to check whether SELinux is enabled on the Docker daemon: {e}"),
+    }
+}
+
+/// Validates a MAC address, such as `02:42:ac:11:00:02`.
+fn validate_mac_address(mac_address: &str) -> Result<()> {
+    let parts: Vec<_> = mac_address.split(':').collect();
+    if parts.len() != 6
+        || !parts
+            .iter()
+            .all(|part| part.len() == 2 && part.bytes().all(|b| b.is_ascii_hexdigit()))
+    {
+        return Err(Error::InvalidBuilderField(
+            "mac_address",
+            format!("`{mac_address}` is not six colon-separated pairs of hexadecimal digits"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Validates a timezone name against the timezone database in `zoneinfo`.
+///
+/// Only the form of the name is checked if the database does not exist.
+fn validate_timezone(timezone: &str, zoneinfo: &Path) -> Result<()> {
+    let valid = !timezone.is_empty()
+        && timezone.split('/').all(|part| {
+            !part.is_empty()
+                && part != ".."
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '.'))
+        });
+
+    if !valid {
+        return Err(Error::InvalidBuilderField(
+            "timezone",
+            format!("`{timezone}` is not a valid timezone name"),
+        ));
+    }
+
+    if zoneinfo.is_dir() && !zoneinfo.join(timezone).is_file() {
+        return Err(Error::InvalidBuilderField(
+            "timezone",
+            format!(
+                "`{timezone}` is not in the timezone database at `{zoneinfo}`",
+                zoneinfo = zoneinfo.display()
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Validates a seccomp profile, which must be `unconfined`, `builtin`, or a
+/// JSON object.
+fn validate_seccomp_profile(profile: &str) -> Result<()> {
+    if matches!(profile, "unconfined" | "builtin") {
+        return Ok(());
+    }
+
+    match serde_json::from_str::<serde_json::Value>(profile) {
+        Ok(serde_json::Value::Object(_)) => Ok(()),
+        Ok(_) => Err(Error::InvalidBuilderField(
+            "seccomp_profile",
+            String::from("profile is not a JSON object"),
+        )),
+        Err(e) => Err(Error::InvalidBuilderField(
+            "seccomp_profile",
+            format!("profile is not valid JSON: {e}"),
+        )),
+    }
+}
+
+/// Validates a cpuset list, such as `0-3,7`.
+fn validate_cpuset(field: &'static str, cpuset: &str) -> Result<()> {
+    let invalid = || {
+        Error::InvalidBuilderField(
+            field,
+            format!("`{cpuset}` is not a comma-separated list of numbers and ranges"),
+        )
+    };
+
+    for item in cpuset.split(',') {
+        let (start, end) = item.split_once('-').unwrap_or((item, item));
+        let start = start.parse::<u32>().map_err(|_| invalid())?;
+        let end = end.parse::<u32>().map_err(|_| invalid())?;
+
+        if start > end {
+            return Err(Error::InvalidBuilderField(
+                field,
+                format!("range `{item}` in `{cpuset}` is reversed"),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a client for building configurations; it never connects.
+    fn client() -> Docker {
+        Docker::connect_with_http("http://localhost:2375", 4, bollard::API_DEFAULT_VERSION).unwrap()
+    }
+
+    #[test]
+    fn config() {
+        let (config, host_config) = Builder::new(client())
+            .image("nginx")
+            .program("nginx")
+            .args(["-g", "daemon off;"])
+            .env("A", "1")
+            .label("app", "web")
+            .publish_port("80/tcp", Some(8080))
+            .to_config();
+
+        assert_eq!(config.image.as_deref(), Some("nginx"));
+        assert_eq!(config.cmd.unwrap(), ["nginx", "-g", "daemon off;"]);
+        assert_eq!(config.env.unwrap(), ["A=1"]);
+        assert_eq!(config.labels.unwrap()["app"], "web");
+        assert!(config.exposed_ports.unwrap().contains_key("80/tcp"));
+        assert!(config.host_config.is_none());
+
+        let host_config = host_config.unwrap();
+        assert!(host_config.network_mode.is_none());
+
+        let bindings = host_config.port_bindings.unwrap();
+        assert_eq!(
+            bindings["80/tcp"].as_ref().unwrap()[0].host_port.as_deref(),
+            Some("8080")
+        );
+    }
+
+    #[test]
+    fn secret_env() {
+        let builder = Builder::new(client())
+            .env("A", "1")
+            .secret_env("TOKEN", "hunter2")
+            .env("B", "2")
+            .secret_env("B", "secret");
+
+        let (config, _) = builder.to_config();
+        assert_eq!(
+            config.env.unwrap(),
+            ["A=1", "TOKEN=<redacted>", "B=<redacted>"]
+        );
+        assert_eq!(
+            builder.create_body().unwrap().env.unwrap(),
+            ["A=1", "TOKEN=hunter2", "B=secret"]
+        );
+
+        let builder = builder.env("TOKEN", "public");
+        assert_eq!(
+            builder.env_vars(true),
+            ["A=1", "TOKEN=public", "B=<redacted>"]
+        );
+    }
+
+    #[test]
+    fn env_passthrough() {
+        // SAFETY: the variable is only used by this test, so nothing else reads
+        // or writes it concurrently
+        unsafe {
+            std::env::set_var("CRANKSHAFT_DOCKER_PASSTHROUGH_VARIABLE", "value");
+        }
+
+        let builder = Builder::new(client()).env_passthrough(&[
+            "CRANKSHAFT_DOCKER_PASSTHROUGH_VARIABLE",
+            "CRANKSHAFT_DOCKER_UNSET_VARIABLE",
+        ]);
+
+        assert_eq!(builder.env.len(), 1);
+        assert_eq!(
+            builder.env["CRANKSHAFT_DOCKER_PASSTHROUGH_VARIABLE"],
+            "value"
+        );
+    }
+
+    #[test]
+    fn networks() {
+        let (config, host_config) = Builder::new(client())
+            .network_with_aliases("frontend", ["web"])
+            .network("backend")
+            .to_config();
+
+        let endpoints = config.networking_config.unwrap().endpoints_config.unwrap();
+        assert_eq!(endpoints.len(), 1);
+        assert_eq!(
+            endpoints["frontend"].aliases.as_deref(),
+            Some(&[String::from("web")][..])
+        );
+        assert_eq!(
+            host_config.unwrap().network_mode.as_deref(),
+            Some("frontend")
+        );
+    }
+
+    #[test]
+    fn binds() {
+        let (_, host_config) = Builder::new(client())
+            .bind("/data", "/data", false, None)
+            .bind("/config", "/config", true, None)
+            .bind("/shared", "/shared", false, Some(SeLinuxRelabel::Shared))
+            .bind("/private", "/private", true, Some(SeLinuxRelabel::Private))
+            .to_config();
+
+        let host_config = host_config.unwrap();
+        assert_eq!(
+            host_config.binds.as_deref().unwrap(),
+            [
+                "/data:/data",
+                "/config:/config:ro",
+                "/shared:/shared:z",
+                "/private:/private:ro,Z",
+            ]
+        );
+        assert!(relabels_binds(&host_config));
+
+        let config = |binds: &[&str]| HostConfig {
+            binds: Some(binds.iter().map(|b| b.to_string()).collect()),
+            ..Default::default()
+        };
+        assert!(!relabels_binds(&config(&["/data:/data", "/z:/z:ro"])));
+        assert!(!relabels_binds(&HostConfig::default()));
+    }
+
+    #[test]
+    fn sysctls() {
+        let (_, host_config) = Builder::new(client())
+            .sysctl("net.ipv4.ip_forward", "1")
+            .allow_privileged_ports()
+            .to_config();
+
+        let sysctls = host_config.unwrap().sysctls.unwrap();
+        assert_eq!(sysctls.len(), 2);
+        assert_eq!(sysctls["net.ipv4.ip_forward"], "1");
+        assert_eq!(sysctls["net.ipv4.ip_unprivileged_port_start"], "0");
+    }
+
+    #[test]
+    fn no_network() {
+        let (config, host_config) = Builder::new(client())
+            .network("frontend")
+            .no_network()
+            .to_config();
+
+        assert!(config.networking_config.is_none());
+        assert_eq!(host_config.unwrap().network_mode.as_deref(), Some("none"));
+    }
+
+    #[test]
+    fn read_only_rootfs() {
+        let (_, host_config) = Builder::new(client())
+            .read_only_rootfs_with_writable(["/var/cache", "/tmp", "/var/cache"])
+            .to_config();
+
+        let host_config = host_config.unwrap();
+        assert_eq!(host_config.readonly_rootfs, Some(true));
+
+        let mounts = host_config.mounts.unwrap();
+        assert_eq!(
+            mounts
+                .iter()
+                .map(|mount| mount.target.as_deref().unwrap())
+                .collect::<Vec<_>>(),
+            ["/tmp", "/run", "/var/cache"]
+        );
+        assert!(
+            mounts
+                .iter()
+                .all(|mount| mount.typ == Some(MountTypeEnum::TMPFS))
+        );
+    }
+
+    #[test]
+    fn mac_addresses() {
+        assert!(validate_mac_address("02:42:ac:11:00:02").is_ok());
+        assert!(validate_mac_address("02:42:AC:11:00:02").is_ok());
+        assert!(validate_mac_address("02-42-ac-11-00-02").is_err());
+        assert!(validate_mac_address("02:42:ac:11:00").is_err());
+        assert!(validate_mac_address("02:42:ac:11:00:02:03").is_err());
+        assert!(validate_mac_address("02:42:ac:11:00:2").is_err());
+        assert!(validate_mac_address("02:42:ac:11:00:zz").is_err());
+
+        let (config, _) = Builder::new(client())
+            .mac_address("02:42:ac:11:00:02")
+            .to_config();
+        assert_eq!(config.mac_address.as_deref(), Some("02:42:ac:11:00:02"));
+
+        let (config, _) = Builder::new(client())
+            .network("frontend")
+            .mac_address("02:42:ac:11:00:02")
+            .to_config();
+        assert_eq!(config.mac_address, None);
+        assert_eq!(
+            config.networking_config.unwrap().endpoints_config.unwrap()["frontend"]
+                .mac_address
+                .as_deref(),
+            Some("02:42:ac:11:00:02")
+        );
+    }
+
+    #[test]
+    fn timezones() {
+        let zoneinfo = tempfile::tempdir().unwrap();
+        std::fs::create_dir(zoneinfo.path().join("Europe")).unwrap();
+        std::fs::write(zoneinfo.path().join("Europe/Berlin"), b"TZif").unwrap();
+        std::fs::write(zoneinfo.path().join("UTC"), b"TZif").unwrap();
+
+        assert!(validate_timezone("Europe/Berlin", zoneinfo.path()).is_ok());
+        assert!(validate_timezone("UTC", zoneinfo.path()).is_ok());
+        assert!(validate_timezone("Europe/Atlantis", zoneinfo.path()).is_err());
+        assert!(validate_timezone("Europe", zoneinfo.path()).is_err());
+        assert!(validate_timezone("", zoneinfo.path()).is_err());
+        assert!(validate_timezone("../etc/passwd", zoneinfo.path()).is_err());
+        assert!(validate_timezone("/Europe/Berlin", zoneinfo.path()).is_err());
+
+        let missing = zoneinfo.path().join("missing");
+        assert!(validate_timezone("Europe/Atlantis", &missing).is_ok());
+        assert!(validate_timezone("Europe/../UTC", &missing).is_err());
+
+        let (config, host_config) = Builder::new(client())
+            .timezone("Europe/Berlin", true)
+            .to_config();
+
+        assert_eq!(config.env.unwrap(), ["TZ=Europe/Berlin"]);
+        assert_eq!(
+            host_config.unwrap().binds.unwrap(),
+            ["/usr/share/zoneinfo/Europe/Berlin:/etc/localtime:ro"]
+        );
+    }
+
+    #[test]
+    fn security_opts() {
+        let (_, host_config) = Builder::new(client())
+            .apparmor_profile("docker-default")
+            .security_label("type:svirt_apache_t")
+            .security_label("level:s0:c100,c200")
+            .seccomp_profile("unconfined")
+            .to_config();
+
+        assert_eq!(
+            host_config.unwrap().security_opt.unwrap(),
+            [
+                "apparmor=docker-default",
+                "label=type:svirt_apache_t",
+                "label=level:s0:c100,c200",
+                "seccomp=unconfined",
+            ]
+        );
+    }
+
+    #[test]
+    fn blkio_weight() {
+        let config = |weight| HostConfig {
+            blkio_weight: Some(weight),
+            ..Default::default()
+        };
+
+        assert!(validate_host_config(&config(10)).is_ok());
+        assert!(validate_host_config(&config(1000)).is_ok());
+        assert!(validate_host_config(&config(9)).is_err());
+        assert!(validate_host_config(&config(1001)).is_err());
+    }
+
+    #[test]
+    fn cpu_bandwidth() {
+        let config = |period, quota| HostConfig {
+            cpu_period: Some(period),
+            cpu_quota: Some(quota),
+            ..Default::default()
+        };
+
+        assert!(validate_host_config(&config(1000, 1000)).is_ok());
+        assert!(validate_host_config(&config(1_000_000, 4_000_000)).is_ok());
+        assert!(validate_host_config(&config(999, 1000)).is_err());
+        assert!(validate_host_config(&config(1_000_001, 1000)).is_err());
+        assert!(validate_host_config(&config(100_000, 999)).is_err());
+        assert!(validate_host_config(&config(100_000, 1)).is_err());
+        assert!(validate_host_config(&config(100_000, 0)).is_ok());
+        assert!(validate_host_config(&config(100_000, -1)).is_ok());
+    }
+
+    #[test]
+    fn seccomp_profiles() {
+        let config = |profile: &str| HostConfig {
+            security_opt: Some(vec![
+                String::from("no-new-privileges"),
+                format!("seccomp={profile}"),
+            ]),
+            ..Default::default()
+        };
+
+        assert!(validate_host_config(&config("unconfined")).is_ok());
+        assert!(validate_host_config(&config("builtin")).is_ok());
+        assert!(validate_host_config(&config(r#"{"defaultAction": "SCMP_ACT_ERRNO"}"#)).is_ok());
+        assert!(validate_host_config(&config(r#"{"defaultAction": "#)).is_err());
+        assert!(validate_host_config(&config("[]")).is_err());
+        assert!(validate_host_config(&config("default")).is_err());
+    }
+
+    #[test]
+    fn cpusets() {
+        assert!(validate_cpuset("cpuset_cpus", "0").is_ok());
+        assert!(validate_cpuset("cpuset_cpus", "0-3,7").is_ok());
+        assert!(validate_cpuset("cpuset_cpus", "").is_err());
+        assert!(validate_cpuset("cpuset_cpus", "0-").is_err());
+        assert!(validate_cpuset("cpuset_cpus", "1,,2").is_err());
+        assert!(validate_cpuset("cpuset_cpus", "3-1").is_err());
+        assert!(validate_cpuset("cpuset_cpus", "a").is_err());
+    }
+
+    #[test]
+    fn raw_config() {
+        let body = Builder::new(client())
+            .image("alpine")
+            .program("true")
+            .init(true)
+            .cpuset_cpus("0")
+            .raw_host_config(serde_json::json!({ "ShmSize": 1024, "Init": false }))
+            .raw_config(serde_json::json!({
+                "StopSignal": "SIGINT",
+                "Image": "busybox",
+                "HostConfig": { "CpusetCpus": null },
+                "Unknown": { "Field": true },
+            }))
+            .create_body()
+            .unwrap();
+
+        assert_eq!(body.image.as_deref(), Some("busybox"));
+        assert_eq!(body.stop_signal.as_deref(), Some("SIGINT"));
+        assert_eq!(body.cmd.unwrap(), ["true"]);
+
+        let host_config = body.host_config.unwrap();
+        assert_eq!(host_config.shm_size, Some(1024));
+        assert_eq!(host_config.init, Some(false));
+        assert!(host_config.cpuset_cpus.is_none());
+
+        let mut dropped = Vec::new();
+        dropped_fields(
+            &serde_json::json!({ "A": { "B": 1, "C": null }, "D": 2 }),
+            &serde_json::json!({ "A": {}, "D": 2 }),
+            "",
+            &mut dropped,
+        );
+        assert_eq!(dropped, ["A.B"]);
+    }
+
+    #[test]
+    fn image_digests() {
+        let digest = format!("sha256:{}", "a".repeat(64));
+        assert!(validate_digest(&digest).is_ok());
+        assert!(validate_digest(&format!("sha256:{}", "A".repeat(64))).is_err());
+        assert!(validate_digest("sha256:abc").is_err());
+        assert!(validate_digest(&format!("sha512:{}", "a".repeat(64))).is_err());
+
+        assert_eq!(pin_image("ubuntu", &digest), format!("ubuntu@{digest}"));
+        assert_eq!(
+            pin_image("ubuntu:24.04@sha256:0", &digest),
+            format!("ubuntu:24.04@{digest}")
+        );
+
+        let other = format!("sha256:{}", "b".repeat(64));
+        let image = ImageInspect {
+            id: Some(other.clone()),
+            repo_digests: Some(vec![format!("ubuntu@{digest}")]),
+            ..Default::default()
+        };
+        assert!(digest_matches(&image, &digest));
+        assert!(digest_matches(&image, &other));
+        assert!(!digest_matches(
+            &image,
+            &format!("sha256:{}", "c".repeat(64))
+        ));
+    }
+
+    #[test]
+    fn mounts() {
+        let (_, host_config) = Builder::new(client())
+            .mount(Mount {
+                target: Some(String::from("/data")),
+                source: Some(String::from("/srv/data")),
+                typ: Some(bollard::secret::MountTypeEnum::BIND),
+                consistency: Some(String::from("cached")),
+                bind_options: Some(bollard::secret::MountBindOptions {
+                    propagation: Some(bollard::secret::MountBindOptionsPropagationEnum::RSLAVE),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .to_config();
+
+        let host_config = host_config.unwrap();
+        assert!(validate_host_config(&host_config).is_ok());
+        assert_eq!(
+            host_config.mounts.unwrap()[0].target.as_deref(),
+            Some("/data")
+        );
+
+        let host_config = HostConfig {
+            mounts: Some(vec![Mount::default()]),
+            ..Default::default()
+        };
+        assert!(validate_host_config(&host_config).is_err());
+    }
+
+    #[test]
+    fn rejected_fields() {
+        let error = |message: &str| {
+            Error::from(bollard::errors::Error::DockerResponseServerError {
+                status_code: 500,
+                message: message.to_string(),
+            })
+        };
+
+        assert!(matches!(
+            rejected_field_error(error(
+                "--storage-opt is supported only for overlay over xfs with 'pquota' mount option"
+            )),
+            Error::InvalidBuilderField("storage_opt", _)
+        ));
+        assert!(matches!(
+            rejected_field_error(error("Unknown or invalid runtime name: runsc")),
+            Error::InvalidBuilderField("runtime", _)
+        ));
+        assert!(matches!(
+            rejected_field_error(error("no such image")),
+            Error::ServerError(_)
+        ));
+    }
+
+    #[test]
+    fn reservations() {
+        let config = |reservation, memory: Option<i64>| {
+            let mut builder = Builder::new(client()).memory_reservation(reservation);
+            if let Some(memory) = memory {
+                builder = builder.memory(memory);
+            }
+
+            builder.to_config().1.unwrap()
+        };
+
+        assert!(validate_host_config(&config(1 << 20, None)).is_ok());
+        assert!(validate_host_config(&config(1 << 20, Some(1 << 20))).is_ok());
+        assert!(validate_host_config(&config(1 << 21, Some(0))).is_ok());
+        assert!(validate_host_config(&config(1 << 21, Some(1 << 20))).is_err());
+
+        let config = |shares| HostConfig {
+            cpu_shares: Some(shares),
+            ..Default::default()
+        };
+
+        assert!(validate_host_config(&config(0)).is_ok());
+        assert!(validate_host_config(&config(2)).is_ok());
+        assert!(validate_host_config(&config(262_144)).is_ok());
+        assert!(validate_host_config(&config(1)).is_err());
+        assert!(validate_host_config(&config(262_145)).is_err());
+    }
+
+    #[test]
+    fn oom_kill_disable() {
+        let config = |disable, memory: Option<i64>| {
+            let mut builder = Builder::new(client()).oom_kill_disable(disable);
+            if let Some(memory) = memory {
+                builder = builder.memory(memory);
+            }
+
+            builder.to_config().1.unwrap()
+        };
+
+        assert_eq!(config(true, Some(1 << 30)).memory, Some(1 << 30));
+        assert!(validate_host_config(&config(true, Some(1 << 30))).is_ok());
+        assert!(validate_host_config(&config(false, None)).is_ok());
+        assert!(validate_host_config(&config(true, None)).is_err());
+        assert!(validate_host_config(&config(true, Some(0))).is_err());
+    }
+
+    #[test]
+    fn oom_score_adj() {
+        let config = |adj| HostConfig {
+            oom_score_adj: Some(adj),
+            ..Default::default()
+        };
+
+        assert!(validate_host_config(&config(-1000)).is_ok());
+        assert!(validate_host_config(&config(1000)).is_ok());
+        assert!(validate_host_config(&config(-1001)).is_err());
+        assert!(validate_host_config(&config(1001)).is_err());
     }
 }
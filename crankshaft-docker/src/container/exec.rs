@@ -0,0 +1,142 @@
+//! Commands run within running containers.
+
+use std::process::ExitStatus;
+
+use bollard::Docker;
+use bollard::secret::ExecInspectResponse;
+use tracing::trace;
+
+use super::exit_status;
+use crate::Error;
+use crate::Result;
+
+/// A handle to a command started within a running container by
+/// [`Container::exec()`](crate::Container::exec).
+///
+/// The command keeps running independently of the handle, so the handle can
+/// be dropped for fire-and-forget commands and is only needed to check on
+/// them later.
+#[derive(Clone, Debug)]
+pub struct ExecHandle {
+    /// The client to use for Docker operations.
+    client: Docker,
+
+    /// The name of the container the command runs within.
+    container: String,
+
+    /// The ID of the exec instance.
+    id: String,
+}
+
+impl ExecHandle {
+    /// Creates a new [`ExecHandle`] for an exec instance.
+    pub(crate) fn new(client: Docker, container: String, id: String) -> Self {
+        Self {
+            client,
+            container,
+            id,
+        }
+    }
+
+    /// Gets the ID of the exec instance.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Inspects the state of the command.
+    ///
+    /// This can be polled to wait for a long-running command to exit and to
+    /// get its exit code. The Docker daemon forgets about exec instances when
+    /// their container is removed, after which this fails.
+    pub async fn inspect(&self) -> Result<ExecInspect> {
+        trace!(
+            "inspecting exec `{id}` in container `{container}`",
+            id = self.id,
+            container = self.container
+        );
+
+        self.client
+            .inspect_exec(&self.id)
+            .await
+            .map(|response| ExecInspect::from_response(&response))
+            .map_err(Error::from)
+    }
+}
+
+/// The state of a command started within a container, as returned by
+/// [`ExecHandle::inspect()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExecInspect {
+    /// Whether or not the command is running.
+    pub running: bool,
+
+    /// The exit code of the command.
+    ///
+    /// This is `None` until the command has exited.
+    pub exit_code: Option<i64>,
+
+    /// The process ID of the command on the host.
+    ///
+    /// This is `None` if the daemon did not report one, such as before the
+    /// command has started.
+    pub pid: Option<i64>,
+}
+
+impl ExecInspect {
+    /// Gets the state of a command from the inspection of its exec instance.
+    pub(crate) fn from_response(response: &ExecInspectResponse) -> Self {
+        let running = response.running.unwrap_or_default();
+
+        Self {
+            running,
+            // The daemon may report an exit code of zero for a running command
+            exit_code: response.exit_code.filter(|_| !running),
+            pid: response.pid.filter(|pid| *pid > 0),
+        }
+    }
+
+    /// Gets the exit status of the command, once it has exited.
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.exit_code.map(exit_status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec_inspect() {
+        let running = ExecInspect::from_response(&ExecInspectResponse {
+            running: Some(true),
+            exit_code: Some(0),
+            pid: Some(42),
+            ..Default::default()
+        });
+        assert_eq!(
+            running,
+            ExecInspect {
+                running: true,
+                exit_code: None,
+                pid: Some(42),
+            }
+        );
+        assert!(running.exit_status().is_none());
+
+        let exited = ExecInspect::from_response(&ExecInspectResponse {
+            running: Some(false),
+            exit_code: Some(3),
+            pid: Some(0),
+            ..Default::default()
+        });
+        assert_eq!(
+            exited,
+            ExecInspect {
+                running: false,
+                exit_code: Some(3),
+                pid: None,
+            }
+        );
+        assert_eq!(exited.exit_status().unwrap().code(), Some(3));
+    }
+}
@@ -0,0 +1,124 @@
+//! Finding existing containers.
+
+use std::collections::HashMap;
+
+use bollard::Docker;
+use bollard::query_parameters::InspectContainerOptions;
+use bollard::query_parameters::ListContainersOptions;
+use tracing::debug;
+
+use crate::Container;
+use crate::Error;
+use crate::Result;
+
+/// Finds the running containers whose names match a glob.
+///
+/// See [`Docker::find_containers()`](crate::Docker::find_containers).
+pub(crate) async fn find_containers(docker: &Docker, name_glob: &str) -> Result<Vec<Container>> {
+    debug!("finding running containers matching `{name_glob}`");
+
+    let summaries = docker
+        .list_containers(Some(ListContainersOptions {
+            filters: Some(HashMap::from([(
+                String::from("status"),
+                vec![String::from("running")],
+            )])),
+            ..Default::default()
+        }))
+        .await
+        .map_err(Error::from)?;
+
+    // The Docker daemon prefixes the names of containers with a slash
+    let mut names: Vec<_> = summaries
+        .into_iter()
+        .filter_map(|summary| {
+            summary
+                .names?
+                .into_iter()
+                .map(|name| name.trim_start_matches('/').to_string())
+                .find(|name| glob_matches(name_glob, name))
+        })
+        .collect();
+    names.sort();
+
+    let mut containers = Vec::with_capacity(names.len());
+    for name in names {
+        // The streams to attach to are only known from the container's
+        // configuration, which is not part of the listing
+        let config = match docker
+            .inspect_container(&name, None::<InspectContainerOptions>)
+            .await
+            .map_err(Error::from)
+        {
+            Ok(inspect) => inspect.config.unwrap_or_default(),
+            Err(Error::NotFound(_)) => {
+                debug!("container `{name}` was removed before it could be inspected");
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        containers.push(Container::new(
+            docker.clone(),
+            name,
+            config.attach_stdout.unwrap_or_default(),
+            config.attach_stderr.unwrap_or_default(),
+        ));
+    }
+
+    Ok(containers)
+}
+
+/// Checks whether a name matches a glob, where `*` matches any sequence of
+/// characters and `?` matches any single character.
+fn glob_matches(glob: &str, name: &str) -> bool {
+    let glob: Vec<_> = glob.chars().collect();
+    let name: Vec<_> = name.chars().collect();
+
+    let (mut g, mut n) = (0, 0);
+    // The position of the last `*` in the glob and of the name when it was seen
+    let mut star = None;
+
+    while n < name.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g, n));
+                g += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                g += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Backtrack, letting the last `*` match one more character
+                Some((star_g, star_n)) => {
+                    star = Some((star_g, star_n + 1));
+                    g = star_g + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    glob[g..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs() {
+        assert!(glob_matches("web-*", "web-1"));
+        assert!(glob_matches("web-*", "web-"));
+        assert!(glob_matches("*-worker-?", "job-worker-3"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+        assert!(glob_matches("exact", "exact"));
+        assert!(!glob_matches("web-*", "api-1"));
+        assert!(!glob_matches("*-worker-?", "job-worker-10"));
+        assert!(!glob_matches("exact", "exactly"));
+        assert!(!glob_matches("a*b*c", "aXbYbZ"));
+    }
+}
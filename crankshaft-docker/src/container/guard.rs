@@ -0,0 +1,71 @@
+//! Guards that remove containers when dropped.
+
+use std::ops::Deref;
+
+use tracing::warn;
+
+use crate::Container;
+
+/// A guard that force removes a [`Container`] when dropped.
+///
+/// This ensures containers are not leaked when a panic or an early return
+/// occurs before the container is otherwise removed. Guards are created with
+/// [`Container::guard()`] and dereference to the guarded container.
+///
+/// As removal is asynchronous, dropping the guard spawns a task on the current
+/// Tokio runtime to remove the container; the guard does not wait for that task
+/// to complete. If the guard is dropped outside of a runtime, the container
+/// cannot be removed and a warning is logged instead.
+pub struct ContainerGuard(Option<Container>);
+
+impl ContainerGuard {
+    /// Creates a new guard for the given container.
+    pub(crate) fn new(container: Container) -> Self {
+        Self(Some(container))
+    }
+
+    /// Consumes the guard and returns the container without removing it.
+    pub fn into_inner(mut self) -> Container {
+        // SAFETY: the container is only taken when the guard is consumed or
+        // dropped, so it is always present here.
+        self.0.take().unwrap()
+    }
+}
+
+impl Deref for ContainerGuard {
+    type Target = Container;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the container is only taken when the guard is consumed or
+        // dropped, so it is always present here.
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl Drop for ContainerGuard {
+    fn drop(&mut self) {
+        let Some(container) = self.0.take() else {
+            return;
+        };
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    if let Err(e) = container.force_remove().await {
+                        warn!(
+                            "failed to remove container `{name}` on drop: {e}",
+                            name = container.name
+                        );
+                    }
+                });
+            }
+            Err(_) => {
+                warn!(
+                    "container `{name}` was not removed as its guard was dropped outside of a \
+                     Tokio runtime",
+                    name = container.name
+                );
+            }
+        }
+    }
+}
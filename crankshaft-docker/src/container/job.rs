@@ -0,0 +1,94 @@
+//! Batch jobs that stage inputs into and retrieve outputs from a container.
+
+use std::process::Output;
+
+use indexmap::IndexMap;
+use tracing::warn;
+
+use crate::Container;
+use crate::Docker;
+use crate::Result;
+use crate::container::check_exit;
+
+/// A specification of a batch job run with
+/// [`Docker::run_job()`](crate::Docker::run_job).
+#[derive(Clone, Debug, Default)]
+pub struct JobSpec {
+    /// The image (e.g., `ubuntu:latest`).
+    pub image: String,
+
+    /// The program to run followed by its arguments.
+    pub cmd: Vec<String>,
+
+    /// The input files to upload before the job is run, given as a path,
+    /// contents, and mode.
+    ///
+    /// Paths are resolved against the root directory of the container as with
+    /// [`Container::upload_file()`].
+    pub inputs: Vec<(String, Vec<u8>, u32)>,
+
+    /// The paths of the output files to download once the job has exited
+    /// successfully.
+    pub outputs: Vec<String>,
+}
+
+/// The result of a batch job run with
+/// [`Docker::run_job()`](crate::Docker::run_job).
+#[derive(Debug)]
+pub struct JobResult {
+    /// The exit status and collected output of the job.
+    pub output: Output,
+
+    /// The contents of the output files, by path, in the order they were
+    /// given in [`JobSpec::outputs`].
+    pub outputs: IndexMap<String, Vec<u8>>,
+}
+
+/// Runs a batch job in a new container.
+///
+/// The container is removed once the job has finished, whether or not it
+/// succeeded.
+pub(crate) async fn run_job(docker: &Docker, spec: JobSpec) -> Result<JobResult> {
+    docker.ensure_image(&spec.image).await?;
+
+    let mut cmd = spec.cmd.into_iter();
+    let mut builder = docker
+        .container_builder()
+        .image(spec.image)
+        .attach_stdout()
+        .attach_stderr();
+    if let Some(program) = cmd.next() {
+        builder = builder.program(program).args(cmd);
+    }
+
+    for (path, contents, mode) in spec.inputs {
+        builder = builder.write_file(path, contents, mode);
+    }
+
+    // The inputs are uploaded as the container is built, and the container is
+    // removed if they cannot be. The guard removes the container should this
+    // future be dropped early.
+    let container = builder.try_build("").await?.guard();
+    let result = run(&container, spec.outputs).await;
+
+    let container = container.into_inner();
+    if let Err(e) = container.force_remove().await {
+        warn!("failed to remove container after running a job: {e}");
+    }
+
+    result
+}
+
+/// Runs a job's container and downloads its outputs.
+async fn run(container: &Container, paths: Vec<String>) -> Result<JobResult> {
+    let output = container.run(|| {}).await?;
+    check_exit(&output)?;
+
+    let mut outputs = IndexMap::with_capacity(paths.len());
+    for path in paths {
+        let contents = container.download_file(&path).await?;
+        outputs.insert(path, contents);
+    }
+
+    Ok(JobResult { output, outputs })
+}
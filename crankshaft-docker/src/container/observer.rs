@@ -0,0 +1,37 @@
+//! Observers of container lifecycle events.
+
+use std::time::Instant;
+
+/// An observer of the lifecycle events of a [`Container`](crate::Container).
+///
+/// Observers are notified when a container is created, started, exits, and
+/// is removed, along with the time at which the event occurred. This can be
+/// used to measure, for example, the time from creation to start or the time
+/// spent removing a container. All methods do nothing by default, so
+/// implementations only need to handle the events they are interested in.
+///
+/// Observers are set with
+/// [`Builder::observer()`](crate::container::Builder::observer)
+/// or [`Container::with_observer()`](crate::Container::with_observer). They are
+/// called inline, so they should return quickly.
+pub trait LifecycleObserver: Send + Sync {
+    /// Called when a container has been created.
+    fn created(&self, name: &str, at: Instant) {
+        let _ = (name, at);
+    }
+
+    /// Called when a container has been started.
+    fn started(&self, name: &str, at: Instant) {
+        let _ = (name, at);
+    }
+
+    /// Called when a run container has exited with the given exit code.
+    fn exited(&self, name: &str, exit_code: i64, at: Instant) {
+        let _ = (name, exit_code, at);
+    }
+
+    /// Called when a container has been removed.
+    fn removed(&self, name: &str, at: Instant) {
+        let _ = (name, at);
+    }
+}
@@ -0,0 +1,232 @@
+//! Pipelines of containers that share a scratch volume.
+
+use std::process::Output;
+use std::time::Duration;
+
+use bollard::query_parameters::RemoveVolumeOptions;
+use bollard::secret::Mount;
+use bollard::secret::MountTypeEnum;
+use bollard::secret::VolumeCreateOptions;
+use tracing::debug;
+use tracing::warn;
+
+use crate::Docker;
+use crate::Error;
+use crate::Result;
+use crate::container::check_exit;
+use crate::container::sanitize_name;
+
+/// The prefix of the names of the scratch volumes of pipelines.
+const PIPELINE_VOLUME_PREFIX: &str = "crankshaft-pipeline";
+
+/// The number of attempts made to remove the scratch volume of a pipeline
+/// while it is in use.
+const VOLUME_REMOVE_ATTEMPTS: usize = 5;
+
+/// The delay between attempts to remove the scratch volume of a pipeline.
+const VOLUME_REMOVE_DELAY: Duration = Duration::from_millis(500);
+
+/// A stage of a pipeline run with
+/// [`Docker::run_pipeline()`](crate::Docker::run_pipeline).
+#[derive(Clone, Debug, Default)]
+pub struct Stage {
+    /// The image (e.g., `ubuntu:latest`).
+    pub image: String,
+
+    /// The program to run followed by its arguments.
+    pub cmd: Vec<String>,
+
+    /// The path within the container at which the pipeline's scratch volume
+    /// is mounted (e.g., `/data`).
+    ///
+    /// This must be an absolute path.
+    pub volume_path: String,
+}
+
+/// A guard that removes the scratch volume of a pipeline when dropped.
+///
+/// Like [`ContainerGuard`](crate::ContainerGuard), dropping the guard spawns a
+/// task on the current Tokio runtime to remove the volume.
+struct VolumeGuard<'a> {
+    /// The client to use to remove the volume.
+    docker: &'a Docker,
+
+    /// The name of the volume, until it has been removed.
+    name: Option<String>,
+}
+
+impl VolumeGuard<'_> {
+    /// Removes the volume and disarms the guard.
+    async fn remove(mut self) {
+        // SAFETY: the name is only taken when the guard is consumed or dropped,
+        // so it is always present here.
+        let name = self.name.take().unwrap();
+        remove_volume(self.docker, &name).await;
+    }
+}
+
+impl Drop for VolumeGuard<'_> {
+    fn drop(&mut self) {
+        let Some(name) = self.name.take() else {
+            return;
+        };
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let docker = self.docker.clone();
+                handle.spawn(async move { remove_volume(&docker, &name).await });
+            }
+            Err(_) => {
+                warn!(
+                    "volume `{name}` was not removed as its pipeline was dropped outside of a \
+                     Tokio runtime"
+                );
+            }
+        }
+    }
+}
+
+/// Force removes the scratch volume of a pipeline, logging any failure.
+///
+/// When a pipeline is dropped early, the container of the running stage is
+/// removed concurrently, so removal is retried while the volume is in use.
+async fn remove_volume(docker: &Docker, name: &str) {
+    let mut attempts = 1;
+    loop {
+        match docker
+            .inner()
+            .remove_volume(name, Some(RemoveVolumeOptions { force: true }))
+            .await
+            .map_err(Error::from)
+        {
+            Ok(()) => return,
+            Err(Error::Conflict(_)) if attempts < VOLUME_REMOVE_ATTEMPTS => {
+                attempts += 1;
+                tokio::time::sleep(VOLUME_REMOVE_DELAY).await;
+            }
+            Err(e) => {
+                warn!("failed to remove volume `{name}` after running a pipeline: {e}");
+                return;
+            }
+        }
+    }
+}
+
+/// Validates the stages of a pipeline, reporting the first invalid stage.
+fn validate(stages: &[Stage]) -> Result<()> {
+    if let Some((i, stage)) = stages
+        .iter()
+        .enumerate()
+        .find(|(_, stage)| !stage.volume_path.starts_with('/'))
+    {
+        return Err(Error::InvalidBuilderField(
+            "volume_path",
+            format!(
+                "stage {i} has volume path `{path}`, which is not an absolute path",
+                path = stage.volume_path
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Runs a pipeline of stages that share a scratch volume.
+///
+/// The volume is removed once the pipeline has finished, whether or not it
+/// succeeded, or when the returned future is dropped.
+pub(crate) async fn run_pipeline(docker: &Docker, stages: Vec<Stage>) -> Result<Vec<Output>> {
+    validate(&stages)?;
+
+    if stages.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let volume = docker
+        .inner()
+        .create_volume(VolumeCreateOptions {
+            name: Some(sanitize_name(PIPELINE_VOLUME_PREFIX)),
+            ..Default::default()
+        })
+        .await
+        .map_err(Error::from)?
+        .name;
+
+    debug!(
+        "running a pipeline of {count} stage(s) with volume `{volume}`",
+        count = stages.len()
+    );
+
+    let guard = VolumeGuard {
+        docker,
+        name: Some(volume.clone()),
+    };
+    let result = run(docker, &volume, stages).await;
+    guard.remove().await;
+
+    result
+}
+
+/// Runs the stages of a pipeline in order, stopping at the first stage that
+/// fails.
+async fn run(docker: &Docker, volume: &str, stages: Vec<Stage>) -> Result<Vec<Output>> {
+    let mut outputs = Vec::with_capacity(stages.len());
+    for stage in stages {
+        docker.ensure_image(&stage.image).await?;
+
+        let mut cmd = stage.cmd.into_iter();
+        let mut builder = docker
+            .container_builder()
+            .image(stage.image)
+            .attach_stdout()
+            .attach_stderr()
+            .mount(Mount {
+                target: Some(stage.volume_path),
+                source: Some(volume.to_string()),
+                typ: Some(MountTypeEnum::VOLUME),
+                ..Default::default()
+            });
+        if let Some(program) = cmd.next() {
+            builder = builder.program(program).args(cmd);
+        }
+
+        // The guard removes the container should this future be dropped early
+        let container = builder.try_build("").await?.guard();
+        let output = container.run(|| {}).await;
+
+        let container = container.into_inner();
+        if let Err(e) = container.force_remove().await {
+            warn!("failed to remove container after running a pipeline stage: {e}");
+        }
+
+        let output = output?;
+        check_exit(&output)?;
+        outputs.push(output);
+    }
+
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_volume_paths() {
+        let stage = |volume_path: &str| Stage {
+            image: "ubuntu".to_string(),
+            cmd: vec!["true".to_string()],
+            volume_path: volume_path.to_string(),
+        };
+
+        assert!(validate(&[stage("/data"), stage("/scratch")]).is_ok());
+        assert!(matches!(
+            validate(&[stage("/data"), stage("")]),
+            Err(Error::InvalidBuilderField("volume_path", message)) if message.starts_with("stage 1 ")
+        ));
+        assert!(matches!(
+            validate(&[stage("data")]),
+            Err(Error::InvalidBuilderField("volume_path", _))
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+//! Removal of stale containers.
+
+use std::collections::HashMap;
+
+use bollard::Docker;
+use bollard::query_parameters::ListContainersOptions;
+use bollard::query_parameters::RemoveContainerOptions;
+use futures::stream::FuturesUnordered;
+use tokio_stream::StreamExt as _;
+use tracing::debug;
+use tracing::warn;
+
+use crate::Error;
+use crate::Result;
+
+/// The result of removing the containers matching a label with
+/// [`Docker::reap_by_label()`](crate::Docker::reap_by_label).
+#[derive(Debug, Default)]
+pub struct ReapReport {
+    /// The number of containers that were removed.
+    pub removed: usize,
+
+    /// The containers that could not be removed, by ID, and why.
+    pub errors: Vec<(String, Error)>,
+}
+
+/// Force removes all containers, running or not, with the given label.
+///
+/// A failure to remove one container does not prevent the others from being
+/// removed; such failures are collected in [`ReapReport::errors`].
+pub(crate) async fn reap_by_label(docker: &Docker, key: &str, value: &str) -> Result<ReapReport> {
+    debug!("reaping containers with label `{key}={value}`");
+
+    let containers = docker
+        .list_containers(Some(ListContainersOptions {
+            all: true,
+            filters: Some(HashMap::from([(
+                String::from("label"),
+                vec![format!("{key}={value}")],
+            )])),
+            ..Default::default()
+        }))
+        .await
+        .map_err(Error::from)?;
+
+    let mut futures = containers
+        .into_iter()
+        .filter_map(|container| container.id)
+        .map(|id| async move {
+            let result = docker
+                .remove_container(
+                    &id,
+                    Some(RemoveContainerOptions {
+                        force: true,
+                        ..Default::default()
+                    }),
+                )
+                .await;
+            (id, result)
+        })
+        .collect::<FuturesUnordered<_>>();
+
+    let mut report = ReapReport::default();
+    while let Some((id, result)) = futures.next().await {
+        match result {
+            Ok(()) => report.removed += 1,
+            Err(e) => {
+                warn!("failed to remove container `{id}`: {e}");
+                report.errors.push((id, Error::from(e)));
+            }
+        }
+    }
+
+    debug!(
+        "reaped {removed} containers with label `{key}={value}`",
+        removed = report.removed
+    );
+
+    Ok(report)
+}
@@ -0,0 +1,460 @@
+//! Options for running containers.
+
+use std::process::Output;
+use std::time::Duration;
+
+use bollard::container::LogOutput;
+use bollard::secret::ContainerStatsResponse;
+use tokio::io::AsyncWrite;
+use tokio::task::JoinHandle;
+
+/// Options for running a [`Container`](crate::Container).
+///
+/// See [`Container::run_with_options()`](crate::Container::run_with_options).
+#[derive(Debug, Default)]
+pub struct RunOptions {
+    /// The maximum number of bytes of output to collect.
+    pub(super) max_output_bytes: Option<usize>,
+
+    /// Whether or not the container is stopped once the output limit is
+    /// exceeded.
+    pub(super) stop_on_truncation: bool,
+
+    /// Whether or not standard error is merged into standard output.
+    pub(super) merge_output: bool,
+
+    /// The maximum time to wait for output before killing the container.
+    pub(super) idle_timeout: Option<Duration>,
+
+    /// The sampler of the container's resource usage statistics.
+    pub(super) stats: Option<StatsSampler>,
+
+    /// Whether or not standard output is attached, overriding the container.
+    pub(super) attach_stdout: Option<bool>,
+
+    /// Whether or not standard error is attached, overriding the container.
+    pub(super) attach_stderr: Option<bool>,
+
+    /// The filter of the output to collect.
+    pub(super) filter: Option<LogFilter>,
+
+    /// The heartbeat sent while the container runs.
+    pub(super) heartbeat: Option<Heartbeat>,
+
+    /// The writers the output is also written to as it is received.
+    pub(super) tee: Option<Tee>,
+}
+
+/// A writer the output of a container is written to.
+type TeeWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// The writers standard output and standard error are written to as they are
+/// received.
+pub(super) struct Tee {
+    /// The writer for standard output.
+    pub(super) stdout: TeeWriter,
+
+    /// The writer for standard error.
+    pub(super) stderr: TeeWriter,
+}
+
+impl std::fmt::Debug for Tee {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tee").finish_non_exhaustive()
+    }
+}
+
+/// Calls a callback with the elapsed time at an interval while a container
+/// runs.
+pub(super) struct Heartbeat {
+    /// The interval between heartbeats.
+    pub(super) interval: Duration,
+
+    /// The callback for each heartbeat.
+    pub(super) callback: Box<dyn FnMut(Duration) + Send>,
+}
+
+impl std::fmt::Debug for Heartbeat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Heartbeat")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A filter of the output of a container, which returns whether or not a
+/// frame of output is collected.
+pub(super) struct LogFilter(pub(super) Box<dyn Fn(&LogOutput) -> bool + Send + Sync>);
+
+impl std::fmt::Debug for LogFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LogFilter").finish_non_exhaustive()
+    }
+}
+
+/// A callback for the resource usage statistics of a container.
+type StatsCallback = Box<dyn FnMut(&ContainerStatsResponse) + Send>;
+
+/// Samples the resource usage statistics of a container at an interval.
+pub(super) struct StatsSampler {
+    /// The interval between samples.
+    pub(super) interval: Duration,
+
+    /// The callback for each sample.
+    pub(super) callback: StatsCallback,
+}
+
+impl std::fmt::Debug for StatsSampler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StatsSampler")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Aborts a task when dropped.
+pub(super) struct AbortOnDrop(pub(super) JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl RunOptions {
+    /// Creates a new [`RunOptions`] with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of bytes of output to collect.
+    ///
+    /// The limit applies to the combined size of standard output and standard
+    /// error. Once it is reached, any further output is dropped and counted in
+    /// [`RunOutput::dropped_bytes`].
+    ///
+    /// By default, all output is collected.
+    pub fn max_output_bytes(mut self, max: usize) -> Self {
+        self.max_output_bytes = Some(max);
+        self
+    }
+
+    /// Sets the container to be stopped once the limit set by
+    /// [`Self::max_output_bytes()`] is exceeded.
+    pub fn stop_on_truncation(mut self) -> Self {
+        self.stop_on_truncation = true;
+        self
+    }
+
+    /// Sets the maximum time to wait for output before the container is
+    /// considered to be hung.
+    ///
+    /// The timer is reset whenever output is received on an attached stream.
+    /// If no output is received before the timeout elapses, the container is
+    /// killed and [`Error::IdleTimeout`](crate::Error::IdleTimeout) is
+    /// returned.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the container's resource usage statistics (such as CPU and memory
+    /// usage) to be sampled at the given interval while it runs.
+    ///
+    /// Each sample is passed to `callback`, and the peak memory usage across
+    /// all samples is returned in [`RunOutput::peak_memory`]. Sampling stops
+    /// once the container exits. As usage is sampled, short-lived peaks
+    /// between samples are not observed.
+    pub fn sample_stats(
+        mut self,
+        interval: Duration,
+        callback: impl FnMut(&ContainerStatsResponse) + Send + 'static,
+    ) -> Self {
+        self.stats = Some(StatsSampler {
+            interval,
+            callback: Box::new(callback),
+        });
+        self
+    }
+
+    /// Sets whether or not standard output is attached to and collected.
+    ///
+    /// This overrides, for this run only, whether standard output is attached
+    /// as set when the container was created (see
+    /// [`Builder::attach_stdout()`](crate::container::Builder::attach_stdout)),
+    /// which is used if this is not set.
+    pub fn attach_stdout(mut self, attach: bool) -> Self {
+        self.attach_stdout = Some(attach);
+        self
+    }
+
+    /// Sets whether or not standard error is attached to and collected.
+    ///
+    /// This overrides, for this run only, whether standard error is attached
+    /// as set when the container was created (see
+    /// [`Builder::attach_stderr()`](crate::container::Builder::attach_stderr)),
+    /// which is used if this is not set.
+    pub fn attach_stderr(mut self, attach: bool) -> Self {
+        self.attach_stderr = Some(attach);
+        self
+    }
+
+    /// Sets the container's resource usage statistics to be sampled at the
+    /// given interval while it runs, only to be summarized in
+    /// [`RunOutput::stats`].
+    ///
+    /// This is equivalent to [`Self::sample_stats()`] without a callback.
+    pub fn collect_stats(self, interval: Duration) -> Self {
+        self.sample_stats(interval, |_| {})
+    }
+
+    /// Sets `callback` to be called at the given interval while the container
+    /// runs, with the time elapsed since it was started.
+    ///
+    /// This allows progress to be shown (for example, keeping a spinner alive)
+    /// while waiting for a container that produces little or no output. The
+    /// first heartbeat is sent one interval after the container is started,
+    /// and heartbeats stop once it exits. Unlike
+    /// [`Self::sample_stats()`], no requests are made to the Docker daemon.
+    pub fn heartbeat(
+        mut self,
+        interval: Duration,
+        callback: impl FnMut(Duration) + Send + 'static,
+    ) -> Self {
+        self.heartbeat = Some(Heartbeat {
+            interval,
+            callback: Box::new(callback),
+        });
+        self
+    }
+
+    /// Sets standard output and standard error to also be written to the given
+    /// writers as they are received (for example, to display them live).
+    ///
+    /// The output is still collected into the returned [`Output`], so it can
+    /// be processed once the container exits. Everything that passes
+    /// [`Self::filter()`] is written, including output dropped because of
+    /// [`Self::max_output_bytes()`], and each stream is written to its own
+    /// writer even when [`Self::merge_output()`] is set. The writers are
+    /// flushed once the container exits; if writing fails, the run fails.
+    pub fn tee(
+        mut self,
+        stdout: impl AsyncWrite + Send + Unpin + 'static,
+        stderr: impl AsyncWrite + Send + Unpin + 'static,
+    ) -> Self {
+        self.tee = Some(Tee {
+            stdout: Box::new(stdout),
+            stderr: Box::new(stderr),
+        });
+        self
+    }
+
+    /// Sets a filter of the output to collect.
+    ///
+    /// The filter is called with each frame of output as it is received, which
+    /// includes the stream it was written to, and the frame is only collected
+    /// if the filter returns `true`. Discarded output does not count towards
+    /// [`Self::max_output_bytes()`] and is not reported in
+    /// [`RunOutput::dropped_bytes`], but it does reset
+    /// [`Self::idle_timeout()`].
+    ///
+    /// A frame holds whatever the container wrote in a single write, so it is
+    /// usually, but not always, a single line: a frame may hold several lines
+    /// or only part of one.
+    pub fn filter(mut self, filter: impl Fn(&LogOutput) -> bool + Send + Sync + 'static) -> Self {
+        self.filter = Some(LogFilter(Box::new(filter)));
+        self
+    }
+
+    /// Sets standard error to be merged into standard output.
+    ///
+    /// The output of both streams is collected into [`Output::stdout`] in the
+    /// order it was received from the Docker daemon, and [`Output::stderr`] is
+    /// left empty. This preserves the interleaving of the two streams as they
+    /// would appear on a terminal.
+    pub fn merge_output(mut self) -> Self {
+        self.merge_output = true;
+        self
+    }
+}
+
+/// The result of running a container with
+/// [`Container::run_with_options()`](crate::Container::run_with_options).
+#[derive(Debug)]
+pub struct RunOutput {
+    /// The exit status and collected output of the container.
+    pub output: Output,
+
+    /// The number of bytes of output that were dropped because the limit set
+    /// by [`RunOptions::max_output_bytes()`] was exceeded.
+    pub dropped_bytes: u64,
+
+    /// The peak memory usage of the container in bytes, if it was sampled with
+    /// [`RunOptions::sample_stats()`].
+    ///
+    /// As with `docker stats`, this excludes the inactive page cache, so it
+    /// approximates the resident set size of the container's processes.
+    pub peak_memory: Option<u64>,
+
+    /// A summary of the container's resource usage, if it was sampled with
+    /// [`RunOptions::sample_stats()`] or [`RunOptions::collect_stats()`].
+    pub stats: Option<RunStats>,
+}
+
+/// A summary of the resource usage of a container over a run, aggregated from
+/// samples of its statistics.
+///
+/// Statistics can only be sampled while a container is running, as the
+/// Docker daemon stops reporting them once it exits; the summary therefore
+/// reflects the last sample taken, and usage between samples is only
+/// observed through the cumulative counters reported by the kernel.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunStats {
+    /// The number of samples taken.
+    pub samples: usize,
+
+    /// The peak memory usage in bytes across all samples, excluding the
+    /// inactive page cache.
+    ///
+    /// This is the same as [`RunOutput::peak_memory`].
+    pub peak_memory: Option<u64>,
+
+    /// The maximum memory usage in bytes recorded by the kernel, including the
+    /// page cache.
+    ///
+    /// Unlike [`Self::peak_memory`], this includes peaks between samples, but
+    /// it is only reported on hosts using cgroup v1.
+    pub max_memory_usage: Option<u64>,
+
+    /// The total CPU time consumed by the container as of the last sample.
+    pub cpu_time: Option<Duration>,
+
+    /// The CPU time consumed in user mode as of the last sample.
+    pub user_cpu_time: Option<Duration>,
+
+    /// The CPU time consumed in kernel mode as of the last sample.
+    pub system_cpu_time: Option<Duration>,
+}
+
+impl RunStats {
+    /// Records a sample of a container's statistics.
+    pub(super) fn record(&mut self, stats: &ContainerStatsResponse) {
+        self.samples += 1;
+
+        if let Some(usage) = memory_usage(stats) {
+            self.peak_memory = Some(self.peak_memory.map_or(usage, |peak| peak.max(usage)));
+        }
+
+        if let Some(max) = stats.memory_stats.as_ref().and_then(|m| m.max_usage) {
+            self.max_memory_usage = Some(self.max_memory_usage.map_or(max, |m| m.max(max)));
+        }
+
+        // CPU usage is reported in nanoseconds and only ever increases, so the
+        // latest sample is the most accurate
+        if let Some(cpu) = stats.cpu_stats.as_ref().and_then(|c| c.cpu_usage.as_ref()) {
+            let time = |nanos: Option<u64>| nanos.map(Duration::from_nanos);
+            self.cpu_time = time(cpu.total_usage).or(self.cpu_time);
+            self.user_cpu_time = time(cpu.usage_in_usermode).or(self.user_cpu_time);
+            self.system_cpu_time = time(cpu.usage_in_kernelmode).or(self.system_cpu_time);
+        }
+    }
+}
+
+impl RunOutput {
+    /// Gets whether or not any output was dropped.
+    pub fn truncated(&self) -> bool {
+        self.dropped_bytes > 0
+    }
+}
+
+/// Gets the memory usage of a container from its statistics, excluding the
+/// inactive page cache.
+fn memory_usage(stats: &ContainerStatsResponse) -> Option<u64> {
+    let memory = stats.memory_stats.as_ref()?;
+    let usage = memory.usage?;
+
+    // cgroup v2 reports `inactive_file`; cgroup v1 reports `total_inactive_file`
+    let inactive = memory
+        .stats
+        .as_ref()
+        .and_then(|stats| {
+            stats
+                .get("inactive_file")
+                .or_else(|| stats.get("total_inactive_file"))
+        })
+        .copied()
+        .unwrap_or_default();
+
+    Some(usage.saturating_sub(inactive))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bollard::secret::ContainerCpuStats;
+    use bollard::secret::ContainerCpuUsage;
+    use bollard::secret::ContainerMemoryStats;
+
+    use super::*;
+
+    #[test]
+    fn memory_usages() {
+        let stats = |usage, inactive: Option<(&str, u64)>| ContainerStatsResponse {
+            memory_stats: Some(ContainerMemoryStats {
+                usage: Some(usage),
+                stats: inactive.map(|(k, v)| HashMap::from([(k.to_string(), v)])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(memory_usage(&stats(100, None)), Some(100));
+        assert_eq!(
+            memory_usage(&stats(100, Some(("inactive_file", 30)))),
+            Some(70)
+        );
+        assert_eq!(
+            memory_usage(&stats(100, Some(("total_inactive_file", 120)))),
+            Some(0)
+        );
+        assert_eq!(memory_usage(&ContainerStatsResponse::default()), None);
+    }
+
+    #[test]
+    fn run_stats() {
+        let stats = |usage, max_usage, cpu| ContainerStatsResponse {
+            memory_stats: Some(ContainerMemoryStats {
+                usage: Some(usage),
+                max_usage,
+                ..Default::default()
+            }),
+            cpu_stats: Some(ContainerCpuStats {
+                cpu_usage: Some(ContainerCpuUsage {
+                    total_usage: Some(cpu),
+                    usage_in_usermode: Some(cpu / 2),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut run_stats = RunStats::default();
+        run_stats.record(&stats(300, None, 1_000));
+        run_stats.record(&stats(100, Some(500), 3_000));
+        run_stats.record(&ContainerStatsResponse::default());
+
+        assert_eq!(
+            run_stats,
+            RunStats {
+                samples: 3,
+                peak_memory: Some(300),
+                max_memory_usage: Some(500),
+                cpu_time: Some(Duration::from_nanos(3_000)),
+                user_cpu_time: Some(Duration::from_nanos(1_500)),
+                system_cpu_time: None,
+            }
+        );
+    }
+}
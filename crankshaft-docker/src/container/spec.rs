@@ -0,0 +1,240 @@
+//! Container specifications that can be deserialized from configuration.
+
+use bollard::Docker;
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+use crate::Error;
+use crate::Result;
+use crate::container::Builder;
+
+/// A specification of the common options for creating a container.
+///
+/// Specifications can be deserialized from configuration files (for example,
+/// YAML) and turned into a [`Builder`] with [`Builder::from_spec()`]. Unknown
+/// fields are rejected so that misspelled options are not silently ignored.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContainerSpec {
+    /// The image (e.g., `ubuntu:latest`).
+    pub image: String,
+
+    /// The program to run followed by its arguments.
+    pub cmd: Vec<String>,
+
+    /// The environment variables.
+    pub env: IndexMap<String, String>,
+
+    /// The working directory.
+    pub work_dir: Option<String>,
+
+    /// The paths on the host to bind mount into the container.
+    pub mounts: Vec<MountSpec>,
+
+    /// The ports of the container to publish on the host.
+    pub ports: Vec<PortSpec>,
+}
+
+/// A bind mount within a [`ContainerSpec`].
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MountSpec {
+    /// The path on the host.
+    pub source: String,
+
+    /// The absolute path within the container.
+    pub target: String,
+
+    /// Whether or not the mount is read-only.
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// A published port within a [`ContainerSpec`].
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PortSpec {
+    /// The port within the container.
+    pub container: u16,
+
+    /// The port on the host.
+    ///
+    /// If this is not set, the Docker daemon chooses an available port.
+    #[serde(default)]
+    pub host: Option<u16>,
+
+    /// The protocol of the port.
+    #[serde(default)]
+    pub protocol: PortProtocol,
+}
+
+/// The protocol of a [`PortSpec`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortProtocol {
+    /// TCP.
+    #[default]
+    Tcp,
+    /// UDP.
+    Udp,
+    /// SCTP.
+    Sctp,
+}
+
+impl PortProtocol {
+    /// Gets the name Docker uses for the protocol.
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tcp => "tcp",
+            Self::Udp => "udp",
+            Self::Sctp => "sctp",
+        }
+    }
+}
+
+impl ContainerSpec {
+    /// Validates the specification, reporting the first invalid field.
+    fn validate(&self) -> Result<()> {
+        if self.image.is_empty() {
+            return Err(Error::MissingBuilderField("image"));
+        }
+
+        if self.cmd.is_empty() {
+            return Err(Error::MissingBuilderField("cmd"));
+        }
+
+        if let Some(name) = self
+            .env
+            .keys()
+            .find(|name| name.is_empty() || name.contains('='))
+        {
+            return Err(Error::InvalidBuilderField(
+                "env",
+                format!("`{name}` is not a valid environment variable name"),
+            ));
+        }
+
+        for (i, mount) in self.mounts.iter().enumerate() {
+            if mount.source.is_empty() {
+                return Err(Error::InvalidBuilderField(
+                    "mounts",
+                    format!("mount {i} has an empty source"),
+                ));
+            }
+
+            if !mount.target.starts_with('/') {
+                return Err(Error::InvalidBuilderField(
+                    "mounts",
+                    format!(
+                        "mount {i} has target `{target}`, which is not an absolute path",
+                        target = mount.target
+                    ),
+                ));
+            }
+        }
+
+        if let Some(i) = self.ports.iter().position(|port| port.container == 0) {
+            return Err(Error::InvalidBuilderField(
+                "ports",
+                format!("port {i} has a container port of zero"),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Builder {
+    /// Creates a new [`Builder`] from a [`ContainerSpec`].
+    ///
+    /// The specification is validated first; the returned error names the
+    /// field that is invalid.
+    pub fn from_spec(client: Docker, spec: ContainerSpec) -> Result<Self> {
+        spec.validate()?;
+
+        let mut cmd = spec.cmd.into_iter();
+        // SAFETY: the command was validated to not be empty above.
+        let program = cmd.next().unwrap();
+
+        let mut builder = Self::new(client)
+            .image(spec.image)
+            .program(program)
+            .args(cmd)
+            .envs(spec.env);
+
+        if let Some(work_dir) = spec.work_dir {
+            builder = builder.work_dir(work_dir);
+        }
+
+        for mount in spec.mounts {
+            builder = builder.bind(mount.source, mount.target, mount.read_only, None);
+        }
+
+        for port in spec.ports {
+            builder = builder.publish_port(
+                format!("{}/{}", port.container, port.protocol.as_str()),
+                port.host,
+            );
+        }
+
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> ContainerSpec {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn deserialize() {
+        let spec = parse(
+            r#"{
+                "image": "nginx",
+                "cmd": ["nginx", "-g", "daemon off;"],
+                "env": { "A": "1" },
+                "mounts": [{ "source": "/data", "target": "/data", "read_only": true }],
+                "ports": [{ "container": 80, "host": 8080 }, { "container": 53, "protocol": "udp" }]
+            }"#,
+        );
+
+        assert!(spec.validate().is_ok());
+        assert_eq!(spec.cmd, ["nginx", "-g", "daemon off;"]);
+        assert!(spec.mounts[0].read_only);
+        assert_eq!(spec.ports[0].protocol, PortProtocol::Tcp);
+        assert_eq!(spec.ports[1].protocol, PortProtocol::Udp);
+        assert_eq!(spec.ports[1].host, None);
+
+        assert!(serde_json::from_str::<ContainerSpec>(r#"{ "imag": "nginx" }"#).is_err());
+    }
+
+    #[test]
+    fn invalid_fields() {
+        let field = |json| match parse(json).validate() {
+            Err(Error::MissingBuilderField(field)) | Err(Error::InvalidBuilderField(field, _)) => {
+                field
+            }
+            result => panic!("unexpected result {result:?}"),
+        };
+
+        assert_eq!(field(r#"{ "cmd": ["ls"] }"#), "image");
+        assert_eq!(field(r#"{ "image": "ubuntu" }"#), "cmd");
+        assert_eq!(
+            field(r#"{ "image": "ubuntu", "cmd": ["ls"], "env": { "A=B": "" } }"#),
+            "env"
+        );
+        assert_eq!(
+            field(
+                r#"{ "image": "ubuntu", "cmd": ["ls"], "mounts": [{ "source": "/a", "target": "a" }] }"#
+            ),
+            "mounts"
+        );
+        assert_eq!(
+            field(r#"{ "image": "ubuntu", "cmd": ["ls"], "ports": [{ "container": 0 }] }"#),
+            "ports"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! The timestamps of a container's lifecycle.
+
+use std::time::Duration;
+use std::time::SystemTime;
+
+use bollard::secret::ContainerInspectResponse;
+
+use crate::reconnect::parse_timestamp;
+
+/// The timestamps of a container's lifecycle, as returned by
+/// [`Container::timestamps()`](crate::Container::timestamps).
+///
+/// Timestamps keep the nanosecond precision reported by the Docker daemon.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContainerTimes {
+    /// When the container was created.
+    pub created: Option<SystemTime>,
+
+    /// When the container was last started.
+    ///
+    /// This is `None` if the container has never been started.
+    pub started_at: Option<SystemTime>,
+
+    /// When the container last exited.
+    ///
+    /// This is `None` if the container has never exited.
+    pub finished_at: Option<SystemTime>,
+}
+
+impl ContainerTimes {
+    /// Gets the timestamps from the inspection of a container.
+    pub(crate) fn from_inspect(inspect: &ContainerInspectResponse) -> Self {
+        let state = inspect.state.as_ref();
+
+        Self {
+            // The creation time is a string unless a feature of `bollard`
+            // (such as `buildkit`) parses dates, so it is read from its
+            // serialized form, which is RFC 3339 either way
+            created: serde_json::to_value(inspect.created.as_ref())
+                .ok()
+                .as_ref()
+                .and_then(serde_json::Value::as_str)
+                .and_then(parse_time),
+            started_at: state
+                .and_then(|state| state.started_at.as_deref())
+                .and_then(parse_time),
+            finished_at: state
+                .and_then(|state| state.finished_at.as_deref())
+                .and_then(parse_time),
+        }
+    }
+
+    /// Gets the time between the container being created and started.
+    pub fn scheduling_latency(&self) -> Option<Duration> {
+        self.started_at?.duration_since(self.created?).ok()
+    }
+
+    /// Gets the time between the container being started and exiting.
+    ///
+    /// This is `None` while the container is running, as well as if it has
+    /// been restarted since it last exited.
+    pub fn run_time(&self) -> Option<Duration> {
+        self.finished_at?.duration_since(self.started_at?).ok()
+    }
+}
+
+/// Parses a timestamp reported by the Docker daemon.
+///
+/// The daemon reports unset times as `0001-01-01T00:00:00Z`, so times before
+/// the Unix epoch are treated as unset.
+fn parse_time(timestamp: &str) -> Option<SystemTime> {
+    let (secs, nanos) = parse_timestamp(timestamp)?;
+    let secs = u64::try_from(secs).ok()?;
+    Some(SystemTime::UNIX_EPOCH + Duration::new(secs, nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn container_times() {
+        let inspect: ContainerInspectResponse = serde_json::from_value(serde_json::json!({
+            "Created": "2025-01-02T03:04:05.123456789Z",
+            "State": {
+                "StartedAt": "2025-01-02T03:04:05.5Z",
+                "FinishedAt": "0001-01-01T00:00:00Z",
+            },
+        }))
+        .unwrap();
+
+        let times = ContainerTimes::from_inspect(&inspect);
+
+        let epoch = |secs, nanos| Some(SystemTime::UNIX_EPOCH + Duration::new(secs, nanos));
+        assert_eq!(times.created, epoch(1_735_787_045, 123_456_789));
+        assert_eq!(times.started_at, epoch(1_735_787_045, 500_000_000));
+        assert_eq!(times.finished_at, None);
+        assert_eq!(
+            times.scheduling_latency(),
+            Some(Duration::from_nanos(376_543_211))
+        );
+        assert_eq!(times.run_time(), None);
+
+        assert_eq!(
+            ContainerTimes::from_inspect(&Default::default()),
+            ContainerTimes::default()
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Options for tmpfs mounts.
+
+use bollard::secret::MountTmpfsOptions;
+
+/// The options of a tmpfs mount added with
+/// [`Builder::tmpfs()`](crate::container::Builder::tmpfs).
+///
+/// Unset options use the Docker daemon's defaults: a size limited only by the
+/// host's memory, a mode of `1777`, and a mount that allows executables and
+/// setuid bits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TmpfsOptions {
+    /// The size of the mount in bytes.
+    pub size: Option<u64>,
+
+    /// The permission mode of the mount's root directory (e.g., `0o1777`).
+    pub mode: Option<u32>,
+
+    /// Whether or not files within the mount cannot be executed.
+    ///
+    /// Mounting scratch space with `noexec` prevents binaries written to it
+    /// from being run.
+    pub noexec: bool,
+
+    /// Whether or not setuid and setgid bits of files within the mount are
+    /// ignored.
+    pub nosuid: bool,
+}
+
+impl From<TmpfsOptions> for MountTmpfsOptions {
+    fn from(options: TmpfsOptions) -> Self {
+        let flags: Vec<_> = [(options.noexec, "noexec"), (options.nosuid, "nosuid")]
+            .into_iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, flag)| vec![String::from(flag)])
+            .collect();
+
+        Self {
+            size_bytes: options.size.map(|size| size.try_into().unwrap_or(i64::MAX)),
+            mode: options.mode.map(Into::into),
+            options: (!flags.is_empty()).then_some(flags),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tmpfs_options() {
+        let options = MountTmpfsOptions::from(TmpfsOptions {
+            size: Some(64 << 20),
+            mode: Some(0o1770),
+            noexec: true,
+            nosuid: true,
+        });
+
+        assert_eq!(options.size_bytes, Some(64 << 20));
+        assert_eq!(options.mode, Some(0o1770));
+        assert_eq!(
+            options.options,
+            Some(vec![
+                vec![String::from("noexec")],
+                vec![String::from("nosuid")]
+            ])
+        );
+
+        assert_eq!(
+            MountTmpfsOptions::from(TmpfsOptions::default()),
+            MountTmpfsOptions::default()
+        );
+    }
+}
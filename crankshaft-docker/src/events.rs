@@ -0,0 +1,381 @@
+//! Events from the Docker daemon.
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use bollard::query_parameters::EventsOptions;
+use bollard::query_parameters::InspectContainerOptions;
+use bollard::secret::ContainerState;
+use bollard::secret::ContainerStateStatusEnum;
+use bollard::secret::EventMessage;
+use futures::Stream;
+use tokio_stream::StreamExt as _;
+use tracing::debug;
+
+use crate::Docker;
+use crate::Error;
+use crate::Result;
+use crate::reconnect::format_nanos;
+
+/// A container that is restarting repeatedly.
+///
+/// See [`Docker::watch_restart_loops()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RestartLoop {
+    /// The ID of the container.
+    pub id: String,
+
+    /// The name of the container, if it was reported by the Docker daemon.
+    pub name: Option<String>,
+
+    /// The number of times the container restarted within the window.
+    pub restarts: usize,
+}
+
+/// The status of a container.
+///
+/// See [`Container::status_stream()`](crate::Container::status_stream).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContainerStatus {
+    /// The container has been created but not started.
+    Created,
+    /// The container is running.
+    Running,
+    /// The container is paused.
+    Paused,
+    /// The container has exited.
+    Exited {
+        /// The exit code of the container, if it was reported.
+        exit_code: Option<i64>,
+    },
+    /// The container has been removed.
+    Removed,
+}
+
+impl ContainerStatus {
+    /// Gets the status of a container from its inspected state.
+    fn from_state(state: &ContainerState) -> Option<Self> {
+        match state.status? {
+            ContainerStateStatusEnum::CREATED => Some(Self::Created),
+            ContainerStateStatusEnum::RUNNING => Some(Self::Running),
+            ContainerStateStatusEnum::PAUSED => Some(Self::Paused),
+            // A restarting container has exited and is waiting to be started
+            ContainerStateStatusEnum::RESTARTING
+            | ContainerStateStatusEnum::REMOVING
+            | ContainerStateStatusEnum::EXITED
+            | ContainerStateStatusEnum::DEAD => Some(Self::Exited {
+                exit_code: state.exit_code,
+            }),
+            ContainerStateStatusEnum::EMPTY => None,
+        }
+    }
+
+    /// Gets the status of a container after an event.
+    ///
+    /// Returns `None` for events that do not change the status.
+    fn from_event(event: &EventMessage) -> Option<Self> {
+        match event.action.as_deref()? {
+            "create" => Some(Self::Created),
+            "start" | "unpause" => Some(Self::Running),
+            "pause" => Some(Self::Paused),
+            "die" => Some(Self::Exited {
+                exit_code: event
+                    .actor
+                    .as_ref()
+                    .and_then(|actor| actor.attributes.as_ref())
+                    .and_then(|attributes| attributes.get("exitCode"))
+                    .and_then(|code| code.parse().ok()),
+            }),
+            "destroy" => Some(Self::Removed),
+            _ => None,
+        }
+    }
+}
+
+/// The restart history of a container.
+#[derive(Debug, Default)]
+struct History {
+    /// Whether or not the container has died since it was last started.
+    died: bool,
+
+    /// The times at which the container restarted within the window.
+    restarts: VecDeque<Instant>,
+}
+
+/// Tracks container restarts to detect restart loops.
+#[derive(Debug)]
+struct RestartTracker {
+    /// The number of restarts within the window beyond which a container is
+    /// considered to be in a restart loop.
+    threshold: usize,
+
+    /// The window of time in which restarts are counted.
+    window: Duration,
+
+    /// The restart history of each container, by ID.
+    containers: HashMap<String, History>,
+}
+
+impl RestartTracker {
+    /// Creates a new [`RestartTracker`].
+    fn new(threshold: usize, window: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            containers: Default::default(),
+        }
+    }
+
+    /// Records that a container died.
+    fn died(&mut self, id: &str) {
+        self.containers.entry(id.to_string()).or_default().died = true;
+    }
+
+    /// Records that a container was destroyed, forgetting its history.
+    fn destroyed(&mut self, id: &str) {
+        self.containers.remove(id);
+    }
+
+    /// Records that a container started.
+    ///
+    /// A start is only counted as a restart if the container died before it.
+    /// Returns the number of restarts within the window if it exceeds the
+    /// threshold; the history of the container is then cleared so that the
+    /// loop is reported again only once the threshold is exceeded anew.
+    fn started(&mut self, id: &str, at: Instant) -> Option<usize> {
+        let history = self.containers.get_mut(id)?;
+        if !std::mem::take(&mut history.died) {
+            return None;
+        }
+
+        history.restarts.push_back(at);
+        while history
+            .restarts
+            .front()
+            .is_some_and(|start| at.duration_since(*start) > self.window)
+        {
+            history.restarts.pop_front();
+        }
+
+        let restarts = history.restarts.len();
+        if restarts <= self.threshold {
+            return None;
+        }
+
+        history.restarts.clear();
+        Some(restarts)
+    }
+}
+
+/// Watches for containers that restart more than `threshold` times within
+/// `window`.
+pub(crate) fn watch_restart_loops(
+    docker: &Docker,
+    threshold: usize,
+    window: Duration,
+) -> impl Stream<Item = Result<RestartLoop>> + use<> {
+    debug!("watching for containers restarting more than {threshold} times in {window:?}");
+
+    let mut tracker = RestartTracker::new(threshold, window);
+    docker
+        .inner()
+        .events(Some(EventsOptions {
+            filters: Some(HashMap::from([
+                (String::from("type"), vec![String::from("container")]),
+                (
+                    String::from("event"),
+                    vec![
+                        String::from("die"),
+                        String::from("start"),
+                        String::from("destroy"),
+                    ],
+                ),
+            ])),
+            ..Default::default()
+        }))
+        .filter_map(move |event| {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => return Some(Err(Error::from(e))),
+            };
+
+            let actor = event.actor?;
+            let id = actor.id?;
+
+            match event.action.as_deref()? {
+                "die" => tracker.died(&id),
+                "destroy" => tracker.destroyed(&id),
+                "start" => {
+                    let restarts = tracker.started(&id, Instant::now())?;
+                    debug!("container `{id}` restarted {restarts} times in {window:?}");
+
+                    return Some(Ok(RestartLoop {
+                        id,
+                        name: actor.attributes.and_then(|mut a| a.remove("name")),
+                        restarts,
+                    }));
+                }
+                _ => {}
+            }
+
+            None
+        })
+}
+
+/// Streams the status transitions of a container.
+///
+/// The current status is yielded first, followed by each change in status. The
+/// stream ends once the container has been removed.
+pub(crate) fn status_stream(
+    client: bollard::Docker,
+    name: String,
+) -> impl Stream<Item = Result<ContainerStatus>> + use<> {
+    // Events are replayed from just before the container is inspected so that
+    // no transitions are missed; repeated statuses are skipped below
+    let since = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    let events = client
+        .events(Some(EventsOptions {
+            since: Some(format_nanos(i64::try_from(since).unwrap_or(i64::MAX))),
+            filters: Some(HashMap::from([
+                (String::from("type"), vec![String::from("container")]),
+                (String::from("container"), vec![name.clone()]),
+            ])),
+            ..Default::default()
+        }))
+        .filter_map(|event| match event {
+            Ok(event) => ContainerStatus::from_event(&event).map(Ok),
+            Err(e) => Some(Err(Error::from(e))),
+        });
+
+    let current = futures::stream::once(async move {
+        debug!("watching the status of container `{name}`");
+
+        match client
+            .inspect_container(&name, None::<InspectContainerOptions>)
+            .await
+            .map_err(Error::from)
+        {
+            Ok(container) => Ok(container
+                .state
+                .as_ref()
+                .and_then(ContainerStatus::from_state)),
+            Err(Error::NotFound(_)) => Ok(Some(ContainerStatus::Removed)),
+            Err(e) => Err(e),
+        }
+    })
+    .filter_map(Result::transpose);
+
+    let statuses = futures::StreamExt::scan(
+        current.chain(events),
+        (None, false),
+        |(last, done), status| {
+            if *done {
+                return futures::future::ready(None);
+            }
+
+            let status = match status {
+                Ok(status) if Some(status) == *last => Some(None),
+                Ok(status) => {
+                    *last = Some(status);
+                    *done = status == ContainerStatus::Removed;
+                    Some(Some(Ok(status)))
+                }
+                Err(e) => {
+                    *done = true;
+                    Some(Some(Err(e)))
+                }
+            };
+
+            futures::future::ready(status)
+        },
+    );
+
+    statuses.filter_map(std::convert::identity)
+}
+
+#[cfg(test)]
+mod tests {
+    use bollard::secret::EventActor;
+
+    use super::*;
+
+    #[test]
+    fn statuses() {
+        let event = |action: &str, exit_code: Option<&str>| EventMessage {
+            action: Some(action.to_string()),
+            actor: Some(EventActor {
+                attributes: exit_code
+                    .map(|code| HashMap::from([(String::from("exitCode"), code.to_string())])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            ContainerStatus::from_event(&event("start", None)),
+            Some(ContainerStatus::Running)
+        );
+        assert_eq!(
+            ContainerStatus::from_event(&event("die", Some("137"))),
+            Some(ContainerStatus::Exited {
+                exit_code: Some(137)
+            })
+        );
+        assert_eq!(
+            ContainerStatus::from_event(&event("destroy", None)),
+            Some(ContainerStatus::Removed)
+        );
+        assert_eq!(ContainerStatus::from_event(&event("attach", None)), None);
+
+        let state = ContainerState {
+            status: Some(ContainerStateStatusEnum::EXITED),
+            exit_code: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            ContainerStatus::from_state(&state),
+            Some(ContainerStatus::Exited { exit_code: Some(1) })
+        );
+    }
+
+    #[test]
+    fn restart_loops() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut tracker = RestartTracker::new(2, Duration::from_secs(10));
+
+        // The initial start is not a restart
+        assert_eq!(tracker.started("a", at(0)), None);
+
+        for secs in [1, 2] {
+            tracker.died("a");
+            assert_eq!(tracker.started("a", at(secs)), None);
+        }
+
+        tracker.died("a");
+        assert_eq!(tracker.started("a", at(3)), Some(3));
+
+        // The history is cleared once a loop is reported
+        tracker.died("a");
+        assert_eq!(tracker.started("a", at(4)), None);
+
+        // Restarts outside of the window are not counted
+        for secs in [20, 40, 60] {
+            tracker.died("a");
+            assert_eq!(tracker.started("a", at(secs)), None);
+        }
+
+        // Starting without dying is not a restart
+        assert_eq!(tracker.started("a", at(61)), None);
+
+        tracker.destroyed("a");
+        assert!(tracker.containers.is_empty());
+    }
+}
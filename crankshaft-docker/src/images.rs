@@ -1,22 +1,333 @@
 //! Images.
 
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
 
+use bollard::body_try_stream;
+use bollard::query_parameters::BuildImageOptions;
 use bollard::query_parameters::CreateImageOptions;
+use bollard::query_parameters::ImportImageOptions;
 use bollard::query_parameters::ListImagesOptions;
+use bollard::query_parameters::PushImageOptions;
 use bollard::query_parameters::RemoveImageOptions;
+use bollard::query_parameters::TagImageOptions;
 use bollard::secret::ImageDeleteResponseItem;
+use bollard::secret::ImageInspect;
+use bollard::secret::ImageManifestSummaryKindEnum;
 use bollard::secret::ImageSummary;
+use bollard::secret::OciPlatform;
+use bollard::secret::PushImageInfo;
+use bytes::Bytes;
+use futures::Stream;
 use futures::stream::FuturesUnordered;
+use indexmap::IndexMap;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt as _;
 use tokio_stream::StreamExt as _;
+use tokio_util::io::ReaderStream;
 use tracing::Level;
 use tracing::debug;
 use tracing::enabled;
 use tracing::trace;
+use tracing::warn;
 
 use crate::Docker;
 use crate::Error;
 use crate::Result;
+use crate::auth::RegistryAuth;
+use crate::reconnect::Backoff;
+
+mod context;
+
+pub use context::DockerIgnore;
+pub use context::build_context_tar;
+
+/// The default number of attempts to pull an image with [`PullRetry`].
+const DEFAULT_PULL_ATTEMPTS: u32 = 5;
+
+/// The default delay before the first retry of a pull.
+const DEFAULT_PULL_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The default maximum delay between retries of a pull.
+const DEFAULT_PULL_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Parts of registry error messages that indicate a pull will fail again if
+/// retried.
+const FATAL_PULL_ERRORS: &[&str] = &[
+    "unauthorized",
+    "authentication required",
+    "denied",
+    "not found",
+    "manifest unknown",
+    "invalid reference",
+];
+
+/// Parts of registry error messages that indicate a pull may succeed if
+/// retried.
+const TRANSIENT_PULL_ERRORS: &[&str] = &[
+    "toomanyrequests",
+    "too many requests",
+    "rate limit",
+    "timeout",
+    "timed out",
+    "connection reset",
+    "connection refused",
+    "unexpected eof",
+    "temporarily unavailable",
+    "service unavailable",
+    "bad gateway",
+    "gateway timeout",
+    "internal server error",
+];
+
+/// Options for retrying an image pull that fails with a transient error, used
+/// with [`Docker::ensure_image_with_retry()`].
+///
+/// A pull is retried after a delay that doubles with each failed attempt, up
+/// to [`max_backoff()`](Self::max_backoff), if it fails because of a network
+/// error, a rate limit, or a server error of the registry. Errors that will
+/// recur, such as the registry rejecting the credentials or the image not
+/// existing, are returned immediately.
+///
+/// The Docker daemon does not forward the `Retry-After` header of a rate
+/// limited response from the registry, so the backoff is always used; set
+/// [`max_backoff()`](Self::max_backoff) to cover the registry's rate limit
+/// window if needed.
+#[derive(Clone, Debug)]
+pub struct PullRetry {
+    /// The maximum number of attempts, including the first.
+    max_attempts: u32,
+
+    /// The delay before the first retry.
+    initial_backoff: Duration,
+
+    /// The maximum delay between retries.
+    max_backoff: Duration,
+}
+
+impl Default for PullRetry {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_PULL_ATTEMPTS,
+            initial_backoff: DEFAULT_PULL_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_PULL_MAX_BACKOFF,
+        }
+    }
+}
+
+impl PullRetry {
+    /// Creates new [`PullRetry`] options.
+    ///
+    /// By default, a pull is attempted up to 5 times, the first retry is
+    /// after 1 second, and the delay is capped at 60 seconds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of attempts, including the first.
+    ///
+    /// A value of `1` disables retries.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the maximum delay between retries.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+}
+
+/// An image pulled with [`Docker::pull_image()`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PulledImage {
+    /// The ID of the image (e.g., `sha256:...`).
+    ///
+    /// With the default image store of the Docker daemon, this is the digest
+    /// of the image's configuration, which identifies the exact image pulled
+    /// for the platform even when the reference is to a multi-platform image.
+    pub id: String,
+
+    /// The digest that the reference resolved to in the registry (e.g.,
+    /// `sha256:...`), if the registry reported one.
+    ///
+    /// For a multi-platform image, this is the digest of the image index
+    /// rather than of the platform's manifest (see [`Self::platform_digest`]).
+    /// Pulling `name@digest` with the same platform always resolves to the
+    /// same image, so this is suitable for pinning.
+    pub digest: Option<String>,
+
+    /// The digest of the manifest of the pulled image for its platform (e.g.,
+    /// `sha256:...`).
+    ///
+    /// Only the containerd image store of the Docker daemon reports the
+    /// manifests of an image, so this is `None` with the default image store.
+    pub platform_digest: Option<String>,
+
+    /// The platform of the pulled image (e.g., `linux/arm64/v8`).
+    pub platform: String,
+}
+
+/// The defaults an image uses when running a container.
+///
+/// This is a more convenient view of the configuration returned by
+/// [`Docker::inspect_image()`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImageDefaults {
+    /// The default entrypoint of the image.
+    pub entrypoint: Vec<String>,
+
+    /// The default command of the image.
+    ///
+    /// When the image has an entrypoint, these are the default arguments to
+    /// it.
+    pub cmd: Vec<String>,
+
+    /// The default environment variables of the image.
+    pub env: IndexMap<String, String>,
+
+    /// The ports exposed by the image (e.g., `80/tcp`), sorted.
+    pub exposed_ports: Vec<String>,
+
+    /// The default working directory of the image, if any.
+    pub working_dir: Option<String>,
+
+    /// The default user of the image, if any.
+    pub user: Option<String>,
+}
+
+impl From<ImageInspect> for ImageDefaults {
+    fn from(inspect: ImageInspect) -> Self {
+        let config = inspect.config.unwrap_or_default();
+
+        let mut exposed_ports = config
+            .exposed_ports
+            .unwrap_or_default()
+            .into_keys()
+            .collect::<Vec<_>>();
+        exposed_ports.sort();
+
+        Self {
+            entrypoint: config.entrypoint.unwrap_or_default(),
+            cmd: config.cmd.unwrap_or_default(),
+            env: config
+                .env
+                .unwrap_or_default()
+                .into_iter()
+                .map(|var| match var.split_once('=') {
+                    Some((name, value)) => (name.to_string(), value.to_string()),
+                    None => (var, String::new()),
+                })
+                .collect(),
+            exposed_ports,
+            working_dir: config.working_dir.filter(|dir| !dir.is_empty()),
+            user: config.user.filter(|user| !user.is_empty()),
+        }
+    }
+}
+
+/// Options for building an image with [`Docker::build_image()`].
+#[derive(Clone, Debug, Default)]
+pub struct BuildOptions {
+    /// The tag of the built image (e.g., `app:1.0`).
+    pub(crate) tag: Option<String>,
+
+    /// The path of the Dockerfile within the build context.
+    pub(crate) dockerfile: Option<String>,
+
+    /// The build-time variables.
+    pub(crate) build_args: HashMap<String, String>,
+
+    /// The stage of a multi-stage build to build.
+    pub(crate) target: Option<String>,
+
+    /// Whether or not the build cache is ignored.
+    pub(crate) no_cache: bool,
+
+    /// The credentials for registries that base images are pulled from.
+    pub(crate) auth: Vec<RegistryAuth>,
+
+    /// Whether or not BuildKit is used.
+    pub(crate) buildkit: bool,
+}
+
+impl BuildOptions {
+    /// Creates a new [`BuildOptions`] with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the tag of the built image (e.g., `app:1.0`).
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// Sets the path of the Dockerfile within the build context.
+    ///
+    /// By default, `Dockerfile` is used.
+    pub fn dockerfile(mut self, path: impl Into<String>) -> Self {
+        self.dockerfile = Some(path.into());
+        self
+    }
+
+    /// Sets a build-time variable, as referenced by an `ARG` instruction.
+    pub fn build_arg(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.build_args.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets multiple build-time variables.
+    pub fn build_args(
+        mut self,
+        args: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
+    ) -> Self {
+        self.build_args
+            .extend(args.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Sets the stage of a multi-stage build to build.
+    ///
+    /// By default, the final stage is built.
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Sets the build cache to be ignored.
+    pub fn no_cache(mut self) -> Self {
+        self.no_cache = true;
+        self
+    }
+
+    /// Sets the build to use BuildKit rather than the classic builder.
+    ///
+    /// BuildKit supports features that the classic builder ignores, such as
+    /// cache mounts (`RUN --mount=type=cache`). It requires the `buildkit`
+    /// feature of this crate; without it, or if the Docker daemon fails to
+    /// start a BuildKit build, the classic builder is used instead.
+    pub fn buildkit(mut self) -> Self {
+        self.buildkit = true;
+        self
+    }
+
+    /// Adds the credentials for a registry that base images are pulled from.
+    pub fn auth(mut self, auth: RegistryAuth) -> Self {
+        self.auth.push(auth);
+        self
+    }
+}
 
 /// Gets all of the images stored in the Docker daemon.
 pub(crate) async fn list_images(docker: &Docker) -> Result<Vec<ImageSummary>> {
@@ -29,7 +340,7 @@ pub(crate) async fn list_images(docker: &Docker) -> Result<Vec<ImageSummary>> {
             ..Default::default()
         }))
         .await
-        .map_err(Error::Docker)?;
+        .map_err(Error::from)?;
 
     debug!("found {} images", images.len());
 
@@ -62,8 +373,13 @@ pub(crate) async fn list_images(docker: &Docker) -> Result<Vec<ImageSummary>> {
 /// It does this by:
 ///
 /// * Confirming that the image already exists there, or
-/// * Pulling the image from the remote repository.
-pub(crate) async fn ensure_image(docker: &Docker, image: impl AsRef<str>) -> Result<()> {
+/// * Pulling the image from the remote repository with the given credentials.
+pub(crate) async fn ensure_image(
+    docker: &Docker,
+    image: impl AsRef<str>,
+    auth: Option<&RegistryAuth>,
+    retry: &PullRetry,
+) -> Result<()> {
     let image = image.as_ref();
 
     debug!("ensuring image `{image}` exists locally");
@@ -77,7 +393,7 @@ pub(crate) async fn ensure_image(docker: &Docker, image: impl AsRef<str>) -> Res
             ..Default::default()
         }))
         .await
-        .map_err(Error::Docker)?;
+        .map_err(Error::from)?;
 
     if !results.is_empty() {
         debug!("image `{image}` exists locally");
@@ -93,6 +409,155 @@ pub(crate) async fn ensure_image(docker: &Docker, image: impl AsRef<str>) -> Res
     }
 
     debug!("image `{image}` does not exist locally; attempting to pull from remote");
+    pull_image_with_retry(docker, image, "", auth, retry)
+        .await
+        .map(|_| ())
+}
+
+/// Pulls an image from its registry, retrying the pull if it fails with a
+/// transient error.
+///
+/// Returns the digest that the reference resolved to, if the registry reported
+/// one.
+async fn pull_image_with_retry(
+    docker: &Docker,
+    image: &str,
+    platform: &str,
+    auth: Option<&RegistryAuth>,
+    retry: &PullRetry,
+) -> Result<Option<String>> {
+    let mut backoff = Backoff::new(retry.initial_backoff, retry.max_backoff);
+    let mut attempt = 1;
+
+    loop {
+        match pull_image(docker, image, platform, auth).await {
+            Ok(digest) => return Ok(digest),
+            Err(e) if attempt < retry.max_attempts && is_transient_pull_error(&e) => {
+                let delay = backoff.delay();
+                warn!(
+                    "failed to pull image `{image}` (attempt {attempt} of {max}): {e}; retrying \
+                     in {delay:?}",
+                    max = retry.max_attempts
+                );
+
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Pulls an image for a platform and reports what it resolved to.
+pub(crate) async fn pull_image_for_platform(
+    docker: &Docker,
+    image: &str,
+    platform: Option<&str>,
+    auth: Option<&RegistryAuth>,
+    retry: &PullRetry,
+) -> Result<PulledImage> {
+    debug!(
+        "pulling image `{image}`{platform}",
+        platform = platform
+            .map(|platform| format!(" for platform `{platform}`"))
+            .unwrap_or_default()
+    );
+
+    let digest =
+        pull_image_with_retry(docker, image, platform.unwrap_or_default(), auth, retry).await?;
+    let inspect = inspect_image(docker, image).await?;
+    let platform = image_platform(&inspect);
+
+    // Only the containerd image store lists the manifests of an image
+    let mut filters = HashMap::new();
+    filters.insert(String::from("reference"), vec![image.to_string()]);
+    let platform_digest = docker
+        .inner()
+        .list_images(Some(ListImagesOptions {
+            filters: Some(filters),
+            manifests: true,
+            ..Default::default()
+        }))
+        .await
+        .map_err(Error::from)?
+        .iter()
+        .find_map(|summary| manifest_digest(summary, &platform));
+
+    Ok(PulledImage {
+        id: inspect.id.clone().unwrap_or_default(),
+        digest,
+        platform_digest,
+        platform,
+    })
+}
+
+/// Gets the digest of the manifest of an image for a platform (e.g.,
+/// `linux/arm64/v8`), if the image store reports its manifests.
+fn manifest_digest(summary: &ImageSummary, platform: &str) -> Option<String> {
+    summary
+        .manifests
+        .as_deref()?
+        .iter()
+        .filter(|manifest| {
+            manifest.available && manifest.kind == Some(ImageManifestSummaryKindEnum::IMAGE)
+        })
+        .find(|manifest| {
+            manifest
+                .image_data
+                .as_ref()
+                .is_some_and(|data| oci_platform(&data.platform) == platform)
+        })
+        .and_then(|manifest| manifest.descriptor.digest.clone())
+}
+
+/// Gets an OCI platform as `os/arch[/variant]` (e.g., `linux/arm64/v8`).
+fn oci_platform(platform: &OciPlatform) -> String {
+    join_platform(
+        platform.os.as_deref(),
+        platform.architecture.as_deref(),
+        platform.variant.as_deref(),
+    )
+}
+
+/// Gets the platform of an image (e.g., `linux/arm64/v8`).
+fn image_platform(inspect: &ImageInspect) -> String {
+    join_platform(
+        inspect.os.as_deref(),
+        inspect.architecture.as_deref(),
+        inspect.variant.as_deref(),
+    )
+}
+
+/// Joins the parts of a platform as `os/arch[/variant]`, skipping any that
+/// are unset or empty.
+fn join_platform(os: Option<&str>, architecture: Option<&str>, variant: Option<&str>) -> String {
+    [os, architecture, variant]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Gets the digest from a status update of a pull (e.g., `Digest: sha256:...`).
+fn pull_digest(status: &str) -> Option<&str> {
+    status
+        .strip_prefix("Digest:")
+        .map(str::trim)
+        .filter(|digest| !digest.is_empty())
+}
+
+/// Pulls an image from its registry.
+///
+/// An empty platform pulls the image for the platform of the Docker daemon.
+/// Returns the digest that the reference resolved to, if the registry reported
+/// one.
+async fn pull_image(
+    docker: &Docker,
+    image: &str,
+    platform: &str,
+    auth: Option<&RegistryAuth>,
+) -> Result<Option<String>> {
     let mut stream = docker.inner().create_image(
         Some(CreateImageOptions {
             from_image: Some(image.to_string()),
@@ -101,14 +566,23 @@ pub(crate) async fn ensure_image(docker: &Docker, image: impl AsRef<str>) -> Res
             } else {
                 "latest".into()
             }),
+            platform: platform.to_string(),
             ..Default::default()
         }),
         None,
-        None,
+        auth.cloned().map(Into::into),
     );
 
+    let mut digest = None;
     while let Some(result) = stream.next().await {
-        let update = result.map_err(Error::Docker)?;
+        let update = result.map_err(|e| match e {
+            bollard::errors::Error::DockerStreamError { error } => registry_error(error),
+            e => Error::from(e),
+        })?;
+
+        if let Some(found) = update.status.as_deref().and_then(pull_digest) {
+            digest = Some(found.to_string());
+        }
 
         if enabled!(Level::TRACE) {
             trace!(
@@ -143,7 +617,561 @@ pub(crate) async fn ensure_image(docker: &Docker, image: impl AsRef<str>) -> Res
         }
     }
 
-    Ok(())
+    Ok(digest)
+}
+
+/// Inspects an image in the Docker daemon.
+pub(crate) async fn inspect_image(docker: &Docker, image: impl AsRef<str>) -> Result<ImageInspect> {
+    let image = image.as_ref();
+
+    debug!("inspecting image `{image}`");
+    docker
+        .inner()
+        .inspect_image(image)
+        .await
+        .map_err(Error::from)
+}
+
+/// The `PATH` used to find commands in an image that does not set one.
+///
+/// This is the default `PATH` the Docker daemon sets in Linux containers.
+const DEFAULT_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// Gets the paths a command may be found at within an image, in the order
+/// they are searched.
+///
+/// A command containing a `/` is a path itself, resolved against the working
+/// directory of the image if relative; otherwise, it is searched for within
+/// each directory of the image's `PATH`.
+fn command_candidates(defaults: &ImageDefaults, cmd: &str) -> Vec<String> {
+    if cmd.contains('/') {
+        if cmd.starts_with('/') {
+            return vec![cmd.to_string()];
+        }
+
+        let dir = defaults.working_dir.as_deref().unwrap_or("/");
+        return vec![format!("{dir}/{cmd}", dir = dir.trim_end_matches('/'))];
+    }
+
+    defaults
+        .env
+        .get("PATH")
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_PATH)
+        .split(':')
+        .filter(|dir| dir.starts_with('/'))
+        .map(|dir| format!("{dir}/{cmd}", dir = dir.trim_end_matches('/')))
+        .collect()
+}
+
+/// Checks whether a command exists within an image.
+///
+/// See [`Docker::has_command()`] for more information.
+pub(crate) async fn has_command(
+    docker: &Docker,
+    image: impl AsRef<str>,
+    cmd: impl AsRef<str>,
+) -> Result<bool> {
+    let image = image.as_ref();
+    let cmd = cmd.as_ref();
+
+    debug!("checking whether command `{cmd}` exists in image `{image}`");
+
+    ensure_image(docker, image, None, &PullRetry::new().max_attempts(1)).await?;
+    let candidates = command_candidates(&inspect_image(docker, image).await?.into(), cmd);
+
+    // The container is never started: its filesystem can be read without
+    // running anything within it. The guard removes the container should this
+    // future be dropped early.
+    let container = docker
+        .container_builder()
+        .image(image)
+        .program(cmd)
+        .try_build("")
+        .await?
+        .guard();
+
+    let mut result = Ok(false);
+    for path in &candidates {
+        match container.is_executable(path).await {
+            Ok(false) => continue,
+            found => {
+                result = found;
+                break;
+            }
+        }
+    }
+
+    let container = container.into_inner();
+    if let Err(e) = container.force_remove().await {
+        warn!("failed to remove container after checking for a command: {e}");
+    }
+
+    result
+}
+
+/// Tags an image in the Docker daemon.
+///
+/// If `target` does not specify a tag, a default tag of `latest` will be used.
+pub(crate) async fn tag_image(
+    docker: &Docker,
+    source: impl AsRef<str>,
+    target: impl AsRef<str>,
+) -> Result<()> {
+    let source = source.as_ref();
+    let target = target.as_ref();
+    let (repo, tag) = split_reference(target);
+
+    debug!("tagging image `{source}` as `{target}`");
+    docker
+        .inner()
+        .tag_image(
+            source,
+            Some(TagImageOptions {
+                repo: Some(repo.to_string()),
+                tag: Some(tag.unwrap_or("latest").to_string()),
+            }),
+        )
+        .await
+        .map_err(Error::from)
+}
+
+/// Pushes an image from the Docker daemon to its registry.
+///
+/// If the image does not specify a tag, a default tag of `latest` will be used.
+///
+/// The returned stream yields the progress updates of the push. Errors
+/// reported by the registry are returned as
+/// [`Error::RegistryUnauthorized`] or [`Error::RegistryDenied`] where they can
+/// be identified.
+pub(crate) fn push_image<T: AsRef<str>>(
+    docker: &Docker,
+    image: T,
+    auth: Option<&RegistryAuth>,
+) -> impl Stream<Item = Result<PushImageInfo>> + use<T> {
+    let image = image.as_ref();
+    let (name, tag) = split_reference(image);
+
+    debug!("pushing image `{image}`");
+    docker
+        .inner()
+        .push_image(
+            name,
+            Some(PushImageOptions {
+                tag: Some(tag.unwrap_or("latest").to_string()),
+                ..Default::default()
+            }),
+            auth.cloned().map(Into::into),
+        )
+        .map(|result| {
+            let info = result.map_err(Error::from)?;
+
+            if let Some(error) = &info.error {
+                return Err(registry_error(error.clone()));
+            }
+
+            if enabled!(Level::TRACE) {
+                trace!(
+                    "push update: {}",
+                    [
+                        info.status
+                            .as_ref()
+                            .map(|status| format!("status: {status}")),
+                        info.progress
+                            .as_ref()
+                            .map(|progress| format!("progress: {progress}")),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join("; ")
+                );
+            }
+
+            Ok(info)
+        })
+}
+
+/// Builds an image from a build context given as a tar archive.
+///
+/// Returns the ID of the built image.
+pub(crate) async fn build_image(
+    docker: &Docker,
+    context: Vec<u8>,
+    options: BuildOptions,
+) -> Result<String> {
+    log_build(&options);
+    let context = Bytes::from(context);
+
+    if options.buildkit {
+        if cfg!(feature = "buildkit") {
+            match build(docker, full_body(context.clone()), &options, true).await {
+                Ok(id) => return Ok(id),
+                Err((e, false)) => {
+                    debug!(
+                        "failed to start a BuildKit build; falling back to the classic builder: \
+                         {e}"
+                    )
+                }
+                Err((e, true)) => return Err(e),
+            }
+        } else {
+            warn_without_buildkit();
+        }
+    }
+
+    build(docker, full_body(context), &options, false)
+        .await
+        .map_err(|(e, _)| e)
+}
+
+/// Creates the body of a build request from a build context held in memory.
+fn full_body(context: Bytes) -> impl Stream<Item = std::io::Result<Bytes>> + Send + 'static {
+    futures::stream::iter([Ok(context)])
+}
+
+/// Builds an image from a build context streamed as a tar archive.
+///
+/// Unlike [`build_image()`], a failure to start a BuildKit build is returned
+/// rather than falling back to the classic builder, as the context cannot be
+/// read again. Returns the ID of the built image.
+pub(crate) async fn build_image_from_reader(
+    docker: &Docker,
+    context: impl AsyncRead + Send + 'static,
+    options: BuildOptions,
+) -> Result<String> {
+    log_build(&options);
+
+    let buildkit = options.buildkit && cfg!(feature = "buildkit");
+    if options.buildkit && !buildkit {
+        warn_without_buildkit();
+    }
+
+    // A read error aborts the request, but is reported in place of the error
+    // of the aborted request, which does not say what went wrong
+    let read_error = Arc::new(Mutex::new(None));
+    let body = {
+        let read_error = read_error.clone();
+        ReaderStream::new(context).map(move |result| {
+            result.inspect_err(|e| {
+                // SAFETY: the lock is never held across a panic.
+                *read_error.lock().unwrap() = Some(e.to_string());
+            })
+        })
+    };
+
+    let result = build(docker, body, &options, buildkit).await;
+
+    // SAFETY: the lock is never held across a panic.
+    if let Some(e) = read_error.lock().unwrap().take() {
+        return Err(Error::Message(format!("failed to read build context: {e}")));
+    }
+
+    result.map_err(|(e, _)| e)
+}
+
+/// Logs the start of a build.
+fn log_build(options: &BuildOptions) {
+    debug!(
+        "building image{tag}",
+        tag = options
+            .tag
+            .as_ref()
+            .map(|tag| format!(" `{tag}`"))
+            .unwrap_or_default()
+    );
+}
+
+/// Warns that BuildKit was requested without the `buildkit` feature.
+fn warn_without_buildkit() {
+    warn!(
+        "BuildKit was requested but the `buildkit` feature is not enabled; using the classic \
+         builder"
+    );
+}
+
+/// Runs a build with either BuildKit or the classic builder.
+///
+/// On failure, the error is returned along with whether or not the build had
+/// started, i.e., whether any build output was received before the error.
+async fn build(
+    docker: &Docker,
+    context: impl Stream<Item = std::io::Result<Bytes>> + Send + 'static,
+    options: &BuildOptions,
+    buildkit: bool,
+) -> std::result::Result<String, (Error, bool)> {
+    let credentials = options
+        .auth
+        .iter()
+        .map(|auth| (auth.server().to_string(), auth.clone().into()))
+        .collect::<HashMap<_, _>>();
+
+    #[allow(unused_mut)]
+    let mut build_options = BuildImageOptions {
+        t: options.tag.clone(),
+        dockerfile: options
+            .dockerfile
+            .clone()
+            .unwrap_or_else(|| "Dockerfile".into()),
+        buildargs: (!options.build_args.is_empty()).then(|| options.build_args.clone()),
+        target: options.target.clone().unwrap_or_default(),
+        nocache: options.no_cache,
+        rm: true,
+        ..Default::default()
+    };
+
+    #[cfg(feature = "buildkit")]
+    if buildkit {
+        build_options.version = bollard::query_parameters::BuilderVersion::BuilderBuildKit;
+        build_options.session = Some(build_session_id());
+    }
+
+    #[cfg(not(feature = "buildkit"))]
+    let _ = buildkit;
+
+    let mut stream = docker.inner().build_image(
+        build_options,
+        (!credentials.is_empty()).then_some(credentials),
+        Some(body_try_stream(context)),
+    );
+
+    let mut started = false;
+    let mut id = None;
+    while let Some(info) = stream.next().await {
+        let info = info.map_err(|e| (Error::from(e), started))?;
+
+        if let Some(error) = info.error {
+            return Err((registry_error(error), started));
+        }
+
+        started = true;
+
+        if let Some(text) = &info.stream {
+            trace!("build output: {text}", text = text.trim_end());
+        }
+
+        if let Some(image) = info.aux.and_then(aux_image_id) {
+            id = Some(image);
+        }
+    }
+
+    let id = id.ok_or_else(|| {
+        (
+            Error::Message(String::from(
+                "Docker did not report the ID of the built image",
+            )),
+            started,
+        )
+    })?;
+
+    debug!("built image `{id}`");
+    Ok(id)
+}
+
+/// Gets the ID of a built image from the auxiliary data of a build update.
+#[cfg(not(feature = "buildkit"))]
+fn aux_image_id(aux: bollard::secret::ImageId) -> Option<String> {
+    aux.id
+}
+
+/// Gets the ID of a built image from the auxiliary data of a build update.
+#[cfg(feature = "buildkit")]
+fn aux_image_id(aux: bollard::models::BuildInfoAux) -> Option<String> {
+    match aux {
+        bollard::models::BuildInfoAux::Default(image) => image.id,
+        bollard::models::BuildInfoAux::BuildKit(_) => None,
+    }
+}
+
+/// Creates a unique ID for a BuildKit session.
+#[cfg(feature = "buildkit")]
+fn build_session_id() -> String {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    format!(
+        "crankshaft-{pid}-{nanos}-{count}",
+        pid = std::process::id(),
+        count = COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Loads the images in a tar archive (as created by `docker save`) into the
+/// Docker daemon.
+///
+/// The archive is streamed to the daemon as it is read. Returns the references
+/// of the loaded images; images without a tag are referenced by their ID.
+pub(crate) async fn load_image(
+    docker: &Docker,
+    archive: impl AsyncRead + Send + 'static,
+) -> Result<Vec<String>> {
+    debug!("loading images from archive");
+
+    // The body stream cannot carry errors, so a read error ends the body and
+    // is reported once the daemon responds
+    let read_error = Arc::new(Mutex::new(None));
+    let body = {
+        let read_error = read_error.clone();
+        ReaderStream::new(archive).map_while(move |result| match result {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                // SAFETY: the lock is never held across a panic.
+                *read_error.lock().unwrap() = Some(e);
+                None
+            }
+        })
+    };
+
+    let mut stream = docker
+        .inner()
+        .import_image_stream(ImportImageOptions::default(), body, None);
+
+    let mut loaded = Vec::new();
+    let mut result = Ok(());
+    while let Some(info) = stream.next().await {
+        let info = match info {
+            Ok(info) => info,
+            Err(e) => {
+                result = Err(Error::from(e));
+                break;
+            }
+        };
+
+        if let Some(error) = info.error {
+            result = Err(Error::Message(error));
+            break;
+        }
+
+        if let Some(reference) = info.stream.as_deref().and_then(loaded_reference) {
+            debug!("loaded image `{reference}`");
+            loaded.push(reference.to_string());
+        }
+    }
+
+    // SAFETY: the lock is never held across a panic.
+    if let Some(e) = read_error.lock().unwrap().take() {
+        return Err(Error::Message(format!("failed to read image archive: {e}")));
+    }
+
+    result.map(|_| loaded)
+}
+
+/// Gets the image reference from a line of output of loading an image.
+fn loaded_reference(line: &str) -> Option<&str> {
+    let line = line.trim();
+    line.strip_prefix("Loaded image: ")
+        .or_else(|| line.strip_prefix("Loaded image ID: "))
+        .map(str::trim)
+}
+
+/// Saves an image from the Docker daemon as a tar archive.
+///
+/// The returned stream yields the contents of the archive, which can be
+/// loaded with [`load_image()`].
+pub(crate) fn save_image<T: AsRef<str>>(
+    docker: &Docker,
+    image: T,
+) -> impl Stream<Item = Result<Bytes>> + use<T> {
+    let image = image.as_ref();
+
+    debug!("saving image `{image}`");
+    docker
+        .inner()
+        .export_image(image)
+        .map(|result| result.map_err(Error::from))
+}
+
+/// Saves an image from the Docker daemon as a tar archive written to the
+/// given writer.
+pub(crate) async fn save_image_to(
+    docker: &Docker,
+    image: impl AsRef<str>,
+    mut writer: impl AsyncWrite + Unpin,
+) -> Result<()> {
+    let image = image.as_ref();
+    let mut stream = std::pin::pin!(save_image(docker, image));
+
+    while let Some(bytes) = stream.next().await {
+        writer.write_all(&bytes?).await.map_err(|e| {
+            Error::Message(format!("failed to write archive of image `{image}`: {e}"))
+        })?;
+    }
+
+    writer
+        .flush()
+        .await
+        .map_err(|e| Error::Message(format!("failed to write archive of image `{image}`: {e}")))
+}
+
+/// Splits an image reference into its name and tag, if it has one.
+///
+/// A digest (e.g., `@sha256:...`) is not part of either and is dropped.
+fn split_reference(reference: &str) -> (&str, Option<&str>) {
+    // The colon of a digest's algorithm must not be taken for that of a tag
+    let reference = reference
+        .split_once('@')
+        .map_or(reference, |(reference, _)| reference);
+
+    // A colon before the last slash separates a registry host from its port
+    let start = reference.rfind('/').map(|i| i + 1).unwrap_or(0);
+
+    match reference[start..].rfind(':') {
+        Some(i) => (&reference[..start + i], Some(&reference[start + i + 1..])),
+        None => (reference, None),
+    }
+}
+
+/// Gets whether or not a failed pull may succeed if it is retried.
+fn is_transient_pull_error(e: &Error) -> bool {
+    let matches = |message: &str, parts: &[&str]| {
+        let message = message.to_lowercase();
+        parts.iter().any(|part| message.contains(part))
+    };
+
+    match e {
+        Error::Message(message) => {
+            !matches(message, FATAL_PULL_ERRORS) && matches(message, TRANSIENT_PULL_ERRORS)
+        }
+        Error::RegistryUnauthorized(_)
+        | Error::RegistryDenied(_)
+        | Error::NotFound(_)
+        | Error::BadRequest(_) => false,
+        _ => match e.docker_error() {
+            Some(bollard::errors::Error::DockerResponseServerError {
+                status_code,
+                message,
+            }) => {
+                *status_code == 429
+                    || ((500..600).contains(status_code) && !matches(message, FATAL_PULL_ERRORS))
+            }
+            Some(
+                bollard::errors::Error::RequestTimeoutError
+                | bollard::errors::Error::IOError { .. }
+                | bollard::errors::Error::HyperResponseError { .. },
+            ) => true,
+            _ => false,
+        },
+    }
+}
+
+/// Converts an error message reported by a registry into an [`Error`].
+fn registry_error(message: String) -> Error {
+    let lowercase = message.to_lowercase();
+
+    if lowercase.contains("unauthorized") || lowercase.contains("authentication required") {
+        Error::RegistryUnauthorized(message)
+    } else if lowercase.contains("denied") {
+        Error::RegistryDenied(message)
+    } else {
+        Error::Message(message)
+    }
 }
 
 /// Removes an image from the Docker daemon.
@@ -160,7 +1188,7 @@ pub(crate) async fn remove_image<T: AsRef<str>, U: AsRef<str>>(
         .inner()
         .remove_image(name, None::<RemoveImageOptions>, None)
         .await
-        .map_err(Error::Docker)?;
+        .map_err(Error::from)?;
 
     if enabled!(Level::TRACE) {
         for image in &images {
@@ -200,3 +1228,229 @@ pub(crate) async fn remove_all_images(docker: &Docker) -> Result<Vec<ImageDelete
 
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bollard::secret::ImageConfig;
+    use bollard::secret::ImageManifestSummary;
+    use bollard::secret::ImageManifestSummaryImageData;
+    use bollard::secret::OciDescriptor;
+
+    use super::*;
+
+    #[test]
+    fn command_candidates() {
+        let defaults = ImageDefaults {
+            env: IndexMap::from([(String::from("PATH"), String::from("/usr/bin:/bin/:bin"))]),
+            working_dir: Some(String::from("/app/")),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            super::command_candidates(&defaults, "ls"),
+            ["/usr/bin/ls", "/bin/ls"]
+        );
+        assert_eq!(
+            super::command_candidates(&defaults, "/opt/tool"),
+            ["/opt/tool"]
+        );
+        assert_eq!(
+            super::command_candidates(&defaults, "./run.sh"),
+            ["/app/./run.sh"]
+        );
+        assert_eq!(
+            super::command_candidates(&ImageDefaults::default(), "sh").len(),
+            DEFAULT_PATH.split(':').count()
+        );
+    }
+
+    #[test]
+    fn pulled_images() {
+        assert_eq!(
+            pull_digest("Digest: sha256:0123abcd"),
+            Some("sha256:0123abcd")
+        );
+        assert_eq!(pull_digest("Digest: "), None);
+        assert_eq!(pull_digest("Pull complete"), None);
+
+        let inspect = ImageInspect {
+            os: Some(String::from("linux")),
+            architecture: Some(String::from("arm64")),
+            variant: Some(String::from("v8")),
+            ..Default::default()
+        };
+        assert_eq!(image_platform(&inspect), "linux/arm64/v8");
+
+        let inspect = ImageInspect {
+            os: Some(String::from("linux")),
+            architecture: Some(String::from("amd64")),
+            variant: Some(String::new()),
+            ..Default::default()
+        };
+        assert_eq!(image_platform(&inspect), "linux/amd64");
+    }
+
+    #[test]
+    fn manifest_digests() {
+        let manifest = |digest: &str, arch: &str, kind| ImageManifestSummary {
+            id: digest.to_string(),
+            descriptor: OciDescriptor {
+                digest: Some(digest.to_string()),
+                ..Default::default()
+            },
+            available: true,
+            kind: Some(kind),
+            image_data: Some(ImageManifestSummaryImageData {
+                platform: OciPlatform {
+                    os: Some(String::from("linux")),
+                    architecture: Some(arch.to_string()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let summary = ImageSummary {
+            manifests: Some(vec![
+                manifest(
+                    "sha256:attest",
+                    "arm64",
+                    ImageManifestSummaryKindEnum::ATTESTATION,
+                ),
+                manifest("sha256:amd64", "amd64", ImageManifestSummaryKindEnum::IMAGE),
+                manifest("sha256:arm64", "arm64", ImageManifestSummaryKindEnum::IMAGE),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            manifest_digest(&summary, "linux/arm64").as_deref(),
+            Some("sha256:arm64")
+        );
+        assert_eq!(manifest_digest(&summary, "linux/s390x"), None);
+        assert_eq!(
+            manifest_digest(&ImageSummary::default(), "linux/arm64"),
+            None
+        );
+    }
+
+    #[test]
+    fn loaded_references() {
+        assert_eq!(
+            loaded_reference("Loaded image: ubuntu:22.04\n"),
+            Some("ubuntu:22.04")
+        );
+        assert_eq!(
+            loaded_reference("Loaded image ID: sha256:abc\n"),
+            Some("sha256:abc")
+        );
+        assert_eq!(loaded_reference("Loading layer 1/2\n"), None);
+    }
+
+    #[test]
+    fn image_defaults() {
+        let defaults = ImageDefaults::from(ImageInspect {
+            config: Some(ImageConfig {
+                entrypoint: Some(vec!["/docker-entrypoint.sh".into()]),
+                cmd: Some(vec!["nginx".into(), "-g".into(), "daemon off;".into()]),
+                env: Some(vec!["PATH=/usr/bin:/bin".into(), "EMPTY".into()]),
+                exposed_ports: Some(HashMap::from([
+                    ("443/tcp".into(), HashMap::new()),
+                    ("80/tcp".into(), HashMap::new()),
+                ])),
+                working_dir: Some(String::new()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        assert_eq!(defaults.entrypoint, ["/docker-entrypoint.sh"]);
+        assert_eq!(defaults.cmd, ["nginx", "-g", "daemon off;"]);
+        assert_eq!(defaults.env["PATH"], "/usr/bin:/bin");
+        assert_eq!(defaults.env["EMPTY"], "");
+        assert_eq!(defaults.exposed_ports, ["443/tcp", "80/tcp"]);
+        assert_eq!(defaults.working_dir, None);
+        assert_eq!(defaults.user, None);
+    }
+
+    #[test]
+    fn references() {
+        assert_eq!(split_reference("ubuntu"), ("ubuntu", None));
+        assert_eq!(split_reference("ubuntu:24.04"), ("ubuntu", Some("24.04")));
+        assert_eq!(
+            split_reference("localhost:5000/app"),
+            ("localhost:5000/app", None)
+        );
+        assert_eq!(
+            split_reference("localhost:5000/app:v1"),
+            ("localhost:5000/app", Some("v1"))
+        );
+        assert_eq!(split_reference("app@sha256:abcd"), ("app", None));
+        assert_eq!(
+            split_reference("localhost:5000/app:v1@sha256:abcd"),
+            ("localhost:5000/app", Some("v1"))
+        );
+    }
+
+    #[test]
+    fn transient_pull_errors() {
+        let response = |status_code, message: &str| {
+            Error::from(bollard::errors::Error::DockerResponseServerError {
+                status_code,
+                message: message.to_string(),
+            })
+        };
+
+        assert!(is_transient_pull_error(&registry_error(String::from(
+            "toomanyrequests: You have reached your pull rate limit"
+        ))));
+        assert!(is_transient_pull_error(&registry_error(String::from(
+            "Get \"https://registry-1.docker.io/v2/\": net/http: TLS handshake timeout"
+        ))));
+        assert!(!is_transient_pull_error(&registry_error(String::from(
+            "unauthorized: authentication required"
+        ))));
+        assert!(!is_transient_pull_error(&registry_error(String::from(
+            "manifest unknown: manifest unknown"
+        ))));
+        assert!(!is_transient_pull_error(&registry_error(String::from(
+            "something unexpected"
+        ))));
+
+        assert!(is_transient_pull_error(&response(429, "too many requests")));
+        assert!(is_transient_pull_error(&response(
+            503,
+            "received unexpected HTTP status: 503 Service Unavailable"
+        )));
+        assert!(!is_transient_pull_error(&response(
+            500,
+            "Head \"https://registry-1.docker.io/v2/library/nope/manifests/latest\": \
+             unauthorized: incorrect username or password"
+        )));
+        assert!(!is_transient_pull_error(&response(
+            404,
+            "pull access denied for nope, repository does not exist"
+        )));
+        assert!(is_transient_pull_error(&Error::from(
+            bollard::errors::Error::RequestTimeoutError
+        )));
+    }
+
+    #[test]
+    fn registry_errors() {
+        assert!(matches!(
+            registry_error("unauthorized: authentication required".into()),
+            Error::RegistryUnauthorized(_)
+        ));
+        assert!(matches!(
+            registry_error("denied: requested access to the resource is denied".into()),
+            Error::RegistryDenied(_)
+        ));
+        assert!(matches!(
+            registry_error("received unexpected HTTP status: 500".into()),
+            Error::Message(_)
+        ));
+    }
+}
@@ -0,0 +1,548 @@
+//! Build contexts.
+
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use bytes::Bytes;
+use tokio::io::AsyncRead;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::StreamReader;
+use tracing::debug;
+use tracing::trace;
+
+/// The name of the file listing the paths excluded from a build context.
+const DOCKERIGNORE: &str = ".dockerignore";
+
+/// The name of the default Dockerfile of a build context.
+const DOCKERFILE: &str = "Dockerfile";
+
+/// The capacity of the buffer that a streamed build context is written to
+/// before it is sent to the reader.
+const CONTEXT_CHUNK_SIZE: usize = 0xFFFF;
+
+/// The number of chunks of a streamed build context that are sent before the
+/// writing task waits for the reader.
+const CONTEXT_CHUNKS: usize = 4;
+
+/// A token of a compiled `.dockerignore` pattern.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    /// A literal character.
+    Char(char),
+
+    /// `?`, which matches any character except a separator.
+    AnyChar,
+
+    /// `*`, which matches any sequence of characters except a separator.
+    Star,
+
+    /// `**` within a pattern, which matches zero or more directories.
+    Dirs,
+
+    /// `**` at the end of a pattern, which matches anything.
+    Rest,
+
+    /// A character class (e.g., `[a-z]` or `[^0-9]`).
+    Class {
+        /// Whether or not the class is negated.
+        negated: bool,
+
+        /// The inclusive ranges of characters in the class.
+        ranges: Vec<(char, char)>,
+    },
+}
+
+/// A pattern of a `.dockerignore` file.
+#[derive(Clone, Debug)]
+struct Pattern {
+    /// The cleaned text of the pattern, without any `!` prefix.
+    text: String,
+
+    /// Whether or not the pattern re-includes paths (i.e., starts with `!`).
+    negated: bool,
+
+    /// The compiled tokens of the pattern.
+    tokens: Vec<Token>,
+}
+
+impl Pattern {
+    /// Creates a new pattern from its cleaned text.
+    fn new(text: String, negated: bool) -> Self {
+        let tokens = compile(&text);
+        Self {
+            text,
+            negated,
+            tokens,
+        }
+    }
+
+    /// Checks whether the pattern matches a path or any of its parent
+    /// directories.
+    fn matches(&self, path: &str) -> bool {
+        let chars = path.chars().collect::<Vec<_>>();
+        matches(&self.tokens, &chars)
+            || chars
+                .iter()
+                .enumerate()
+                .any(|(i, c)| *c == '/' && matches(&self.tokens, &chars[..i]))
+    }
+}
+
+/// The patterns of a `.dockerignore` file, which exclude paths from a build
+/// context.
+///
+/// Patterns follow the semantics of the Docker CLI: paths are relative to the
+/// root of the context, `*` and `?` do not match separators, `**` matches any
+/// number of directories, and a pattern starting with `!` re-includes paths
+/// that an earlier pattern excluded. The last pattern that matches a path
+/// decides whether it is excluded, and excluding a directory excludes all of
+/// its contents.
+#[derive(Clone, Debug, Default)]
+pub struct DockerIgnore {
+    /// The patterns, in the order they appear in the file.
+    patterns: Vec<Pattern>,
+}
+
+impl DockerIgnore {
+    /// Parses the contents of a `.dockerignore` file.
+    ///
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn parse(contents: &str) -> Self {
+        let patterns = contents
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                let line = line.trim();
+                let (negated, pattern) = match line.strip_prefix('!') {
+                    Some(pattern) => (true, pattern.trim()),
+                    None => (false, line),
+                };
+
+                // A lone `!` is rejected by the Docker CLI, so it is skipped
+                // rather than treated as a pattern matching the root
+                if pattern.is_empty() {
+                    return None;
+                }
+
+                let pattern = clean(pattern);
+                let pattern = match pattern.strip_prefix('/') {
+                    Some(relative) if !relative.is_empty() => relative.to_string(),
+                    _ => pattern,
+                };
+
+                Some(Pattern::new(pattern, negated))
+            })
+            .collect();
+
+        Self { patterns }
+    }
+
+    /// Checks whether a path relative to the root of the build context is
+    /// excluded by the patterns.
+    pub fn is_excluded(&self, path: &str) -> bool {
+        let path = clean(path);
+        self.patterns.iter().fold(false, |excluded, pattern| {
+            // A pattern can only change the outcome if it excludes a path
+            // that is included so far or re-includes one that is excluded
+            if pattern.negated == excluded && pattern.matches(&path) {
+                !pattern.negated
+            } else {
+                excluded
+            }
+        })
+    }
+
+    /// Checks whether a negated pattern may re-include a path within an
+    /// excluded directory, in which case the directory must still be walked.
+    fn may_include_within(&self, dir: &str) -> bool {
+        let dir = format!("{dir}/");
+        self.patterns.iter().any(|pattern| {
+            pattern.negated && format!("{text}/", text = pattern.text).starts_with(&dir)
+        })
+    }
+
+    /// Ensures that a path is kept in the build context even if it is
+    /// excluded, as the Docker CLI does for the `.dockerignore` file and the
+    /// Dockerfile.
+    fn keep(mut self, path: &str) -> Self {
+        if self.is_excluded(path) {
+            self.patterns.push(Pattern::new(path.to_string(), true));
+        }
+
+        self
+    }
+}
+
+/// Cleans a path the way Go's `filepath.Clean()` does, which is how the
+/// Docker CLI normalizes both patterns and paths before matching them.
+fn clean(path: &str) -> String {
+    let rooted = path.starts_with('/');
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => match parts.last() {
+                Some(&last) if last != ".." => {
+                    parts.pop();
+                }
+                _ if rooted => {}
+                _ => parts.push(part),
+            },
+            part => parts.push(part),
+        }
+    }
+
+    match (rooted, parts.is_empty()) {
+        (true, _) => format!("/{parts}", parts = parts.join("/")),
+        (false, true) => String::from("."),
+        (false, false) => parts.join("/"),
+    }
+}
+
+/// Compiles a cleaned pattern into tokens.
+///
+/// An unterminated character class is treated as literal characters.
+fn compile(pattern: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+
+                // `**/` matches the same directories as `**`
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                }
+
+                tokens.push(if chars.peek().is_some() {
+                    Token::Dirs
+                } else {
+                    Token::Rest
+                });
+            }
+            '*' => tokens.push(Token::Star),
+            '?' => tokens.push(Token::AnyChar),
+            '\\' => tokens.push(Token::Char(chars.next().unwrap_or('\\'))),
+            '[' => {
+                let rest = chars.clone().collect::<String>();
+                match compile_class(&rest) {
+                    Some((token, len)) => {
+                        tokens.push(token);
+                        for _ in 0..len {
+                            chars.next();
+                        }
+                    }
+                    None => tokens.push(Token::Char('[')),
+                }
+            }
+            c => tokens.push(Token::Char(c)),
+        }
+    }
+
+    tokens
+}
+
+/// Compiles a character class from the part of a pattern following its `[`.
+///
+/// Returns the class along with the number of characters it spans, including
+/// the closing `]`, or `None` if the class is not terminated.
+fn compile_class(pattern: &str) -> Option<(Token, usize)> {
+    let mut chars = pattern.chars().peekable();
+    let mut len = 0;
+    let negated = chars.peek() == Some(&'^');
+    if negated {
+        chars.next();
+        len += 1;
+    }
+
+    let mut ranges = Vec::new();
+    loop {
+        let start = match chars.next()? {
+            ']' => return Some((Token::Class { negated, ranges }, len + 1)),
+            '\\' => {
+                len += 1;
+                chars.next()?
+            }
+            c => c,
+        };
+        len += 1;
+
+        let end = if chars.peek() == Some(&'-') && chars.clone().nth(1).is_some_and(|c| c != ']') {
+            chars.next();
+            len += 2;
+            match chars.next()? {
+                '\\' => {
+                    len += 1;
+                    chars.next()?
+                }
+                c => c,
+            }
+        } else {
+            start
+        };
+
+        ranges.push((start, end));
+    }
+}
+
+/// Checks whether compiled tokens match the whole of a path.
+fn matches(tokens: &[Token], path: &[char]) -> bool {
+    let Some((token, rest)) = tokens.split_first() else {
+        return path.is_empty();
+    };
+
+    match token {
+        Token::Char(c) => path.first() == Some(c) && matches(rest, &path[1..]),
+        Token::AnyChar => path.first().is_some_and(|c| *c != '/') && matches(rest, &path[1..]),
+        Token::Class { negated, ranges } => {
+            path.first().is_some_and(|c| {
+                ranges.iter().any(|(start, end)| (start..=end).contains(&c)) != *negated
+            }) && matches(rest, &path[1..])
+        }
+        Token::Star => {
+            for i in 0..=path.len() {
+                if matches(rest, &path[i..]) {
+                    return true;
+                }
+
+                if path.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+
+            false
+        }
+        Token::Dirs => {
+            matches(rest, path)
+                || path
+                    .iter()
+                    .enumerate()
+                    .any(|(i, c)| *c == '/' && matches(rest, &path[i + 1..]))
+        }
+        Token::Rest => true,
+    }
+}
+
+/// Streams a TAR archive of a build context from a directory on the host.
+///
+/// Paths excluded by the `.dockerignore` file at the root of the directory, if
+/// any, are left out of the archive (see [`DockerIgnore`]). As with the Docker
+/// CLI, the `.dockerignore` file and the `Dockerfile` are always kept, as the
+/// daemon needs them; a Dockerfile at another path must not be excluded.
+/// Symbolic links are archived as links rather than followed.
+///
+/// The archive is written by a blocking task as it is read, so only a few
+/// chunks of it are held in memory at once. If the directory cannot be
+/// archived, reading fails with the error. This must be called within a Tokio
+/// runtime.
+pub fn build_context_tar(dir: impl Into<PathBuf>) -> impl AsyncRead + Send + Unpin + 'static {
+    let dir = dir.into();
+    debug!("streaming build context from `{dir}`", dir = dir.display());
+
+    let (tx, rx) = mpsc::channel(CONTEXT_CHUNKS);
+    tokio::task::spawn_blocking(move || {
+        let writer = io::BufWriter::with_capacity(CONTEXT_CHUNK_SIZE, ChunkWriter(tx.clone()));
+        if let Err(e) = write_context(&dir, writer) {
+            // The error can only fail to send if the reader was dropped, in
+            // which case nobody is left to report it to
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+
+    StreamReader::new(ReceiverStream::new(rx))
+}
+
+/// Writes a TAR archive of a build context from a directory on the host.
+fn write_context(dir: &Path, writer: impl Write) -> io::Result<()> {
+    let path = dir.join(DOCKERIGNORE);
+    let ignore = match std::fs::read_to_string(&path) {
+        Ok(contents) => DockerIgnore::parse(&contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => DockerIgnore::default(),
+        Err(e) => return Err(context_error(&path, e)),
+    }
+    .keep(DOCKERIGNORE)
+    .keep(DOCKERFILE);
+
+    let mut tar = tar::Builder::new(writer);
+    tar.follow_symlinks(false);
+    append_dir(&mut tar, dir, Path::new(""), &ignore)?;
+    tar.into_inner()?.flush()
+}
+
+/// Appends the contents of a directory within a build context to a TAR
+/// archive, skipping the excluded paths.
+fn append_dir<W: Write>(
+    tar: &mut tar::Builder<W>,
+    root: &Path,
+    dir: &Path,
+    ignore: &DockerIgnore,
+) -> io::Result<()> {
+    let path = root.join(dir);
+    let mut entries = std::fs::read_dir(&path)
+        .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+        .map_err(|e| context_error(&path, e))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = dir.join(entry.file_name());
+        let relative = name
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let is_dir = entry
+            .file_type()
+            .map_err(|e| context_error(&entry.path(), e))?
+            .is_dir();
+
+        if ignore.is_excluded(&relative) {
+            if is_dir && ignore.may_include_within(&relative) {
+                append_dir(tar, root, &name, ignore)?;
+            } else {
+                trace!("excluding `{relative}` from the build context");
+            }
+
+            continue;
+        }
+
+        if is_dir {
+            tar.append_dir(&name, entry.path())
+                .map_err(|e| context_error(&entry.path(), e))?;
+            append_dir(tar, root, &name, ignore)?;
+        } else {
+            tar.append_path_with_name(entry.path(), &name)
+                .map_err(|e| context_error(&entry.path(), e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the path that failed to be archived to an I/O error.
+fn context_error(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(
+        e.kind(),
+        format!(
+            "failed to archive `{path}` for the build context: {e}",
+            path = path.display()
+        ),
+    )
+}
+
+/// A writer that sends the bytes written to it as chunks of a stream.
+struct ChunkWriter(mpsc::Sender<io::Result<Bytes>>);
+
+impl Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "the reader of the build context was dropped",
+                )
+            })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt as _;
+
+    use super::*;
+
+    #[test]
+    fn dockerignore() {
+        let ignore = DockerIgnore::parse(
+            "# comment\n\n  node_modules  \n/.git\n*.log\n!keep.log\n**/*.tmp\ndocs/**\n!docs/README.md\nbuild/[a-c]?\\*\n",
+        );
+
+        assert!(!ignore.is_excluded("# comment"));
+        assert!(ignore.is_excluded("node_modules"));
+        assert!(ignore.is_excluded("node_modules/pkg/index.js"));
+        assert!(!ignore.is_excluded("src/node_modules"));
+        assert!(ignore.is_excluded(".git/HEAD"));
+        assert!(ignore.is_excluded("./debug.log"));
+        assert!(!ignore.is_excluded("logs/debug.log"));
+        assert!(!ignore.is_excluded("keep.log"));
+        assert!(ignore.is_excluded("a.tmp"));
+        assert!(ignore.is_excluded("a/b/c.tmp"));
+        assert!(ignore.is_excluded("docs/guide.md"));
+        assert!(!ignore.is_excluded("docs/README.md"));
+        assert!(ignore.is_excluded("build/b1*"));
+        assert!(!ignore.is_excluded("build/d1*"));
+        assert!(!ignore.is_excluded("build/b1x"));
+
+        assert!(ignore.may_include_within("docs"));
+        assert!(!ignore.may_include_within("node_modules"));
+    }
+
+    #[test]
+    fn cleaned_paths() {
+        assert_eq!(clean("a/./b//c/"), "a/b/c");
+        assert_eq!(clean("a/../../b"), "../b");
+        assert_eq!(clean("/../a"), "/a");
+        assert_eq!(clean("./"), ".");
+    }
+
+    #[tokio::test]
+    async fn build_context() {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, contents) in [
+            ("Dockerfile", "FROM scratch\n"),
+            (
+                ".dockerignore",
+                "Dockerfile\nnode_modules\n*.log\n!keep.log\n",
+            ),
+            ("src/main.rs", "fn main() {}\n"),
+            ("node_modules/pkg/index.js", "\n"),
+            ("debug.log", "\n"),
+            ("keep.log", "\n"),
+        ] {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+
+        let mut archive = Vec::new();
+        build_context_tar(dir.path())
+            .read_to_end(&mut archive)
+            .await
+            .unwrap();
+
+        let paths = tar::Archive::new(archive.as_slice())
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path().unwrap().into_owned();
+                path.to_string_lossy().trim_end_matches('/').to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                ".dockerignore",
+                "Dockerfile",
+                "keep.log",
+                "src",
+                "src/main.rs"
+            ]
+        );
+
+        let error = build_context_tar(dir.path().join("missing"))
+            .read_to_end(&mut Vec::new())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("failed to archive"));
+    }
+}
@@ -1,68 +1,449 @@
 //! A Docker client that uses [`bollard`].
 
+use std::collections::HashMap;
+use std::process::Output;
+
 use bollard::query_parameters::ListNodesOptions;
 use bollard::secret::ImageDeleteResponseItem;
+use bollard::secret::ImageInspect;
 use bollard::secret::ImageSummary;
 
+pub mod auth;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod container;
+pub mod events;
 pub mod images;
+pub mod logs;
+pub mod networks;
+mod raw;
+pub mod reconnect;
 pub mod service;
+pub mod volumes;
 
 use bollard::secret::Node;
+use bollard::secret::PushImageInfo;
 use bollard::secret::SystemInfo;
+use bytes::Bytes;
+use futures::Stream;
 use thiserror::Error;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tracing::debug;
+use tracing::warn;
 
+pub use crate::auth::RegistryAuth;
 pub use crate::container::Container;
 use crate::images::*;
+use crate::raw::DaemonAddress;
 
 /// A global error within this crate.
 #[derive(Error, Debug)]
 pub enum Error {
-    /// An error from [`bollard`].
+    /// An error from [`bollard`] that is not covered by a more specific
+    /// variant.
+    #[error(transparent)]
+    Docker(bollard::errors::Error),
+    /// The Docker daemon responded that a resource does not exist (status code
+    /// 404).
+    #[error(transparent)]
+    NotFound(bollard::errors::Error),
+    /// The Docker daemon responded that a request conflicts with the state of
+    /// a resource, such as a container name already being in use (status code
+    /// 409).
     #[error(transparent)]
-    Docker(#[from] bollard::errors::Error),
+    Conflict(bollard::errors::Error),
+    /// The Docker daemon responded that a request was invalid (status code
+    /// 400).
+    #[error(transparent)]
+    BadRequest(bollard::errors::Error),
+    /// The Docker daemon responded with an internal error (status code 500).
+    #[error(transparent)]
+    ServerError(bollard::errors::Error),
     /// A required value was missing for a builder field.
     #[error("missing required builder field `{0}`")]
     MissingBuilderField(&'static str),
+    /// A builder field was set to an invalid value.
+    #[error("invalid value for builder field `{0}`: {1}")]
+    InvalidBuilderField(&'static str, String),
+    /// A container could not be created as its name is already in use.
+    ///
+    /// See [`container::Builder::replace_existing()`] to remove the existing
+    /// container instead.
+    #[error("a container named `{name}` already exists")]
+    NameConflict {
+        /// The name of the container.
+        name: String,
+    },
+    /// A container was created from an image that does not match the digest
+    /// it was pinned to.
+    ///
+    /// See [`container::Builder::image_digest()`].
+    #[error("image `{image}` of the container does not match the digest `{expected}`")]
+    ImageDigestMismatch {
+        /// The ID of the image the container was created from.
+        image: String,
+        /// The digest the image was pinned to.
+        expected: String,
+    },
+    /// A file downloaded from a container did not have the expected checksum.
+    #[error("SHA-256 digest of `{path}` is `{actual}` but `{expected}` was expected")]
+    ChecksumMismatch {
+        /// The path of the file within the container.
+        path: String,
+        /// The expected SHA-256 digest as lowercase hexadecimal.
+        expected: String,
+        /// The SHA-256 digest of the downloaded file as lowercase hexadecimal.
+        actual: String,
+    },
+    /// A container exited with a non-zero exit code.
+    #[error("container exited with exit code {code}: {stderr}", stderr = stderr.trim())]
+    NonZeroExit {
+        /// The exit code of the container.
+        code: i32,
+        /// The standard error of the container.
+        stderr: String,
+    },
+    /// An upload was rejected as it exceeded the size limit.
+    #[error("upload of {size} bytes exceeds the limit of {limit} bytes")]
+    UploadTooLarge {
+        /// The size of the upload in bytes.
+        size: u64,
+        /// The size limit in bytes.
+        limit: u64,
+    },
+    /// An operation is not supported by the client or the Docker daemon.
+    ///
+    /// See [`Container::checkpoint()`] for an example.
+    #[error("unsupported operation: {0}")]
+    Unsupported(String),
+    /// A container produced no output within its idle timeout and was killed.
+    #[error("container produced no output for {0:?} and was killed")]
+    IdleTimeout(std::time::Duration),
+    /// A registry rejected a request as unauthenticated.
+    #[error("registry authentication failed: {0}")]
+    RegistryUnauthorized(String),
+    /// A registry denied access to a resource.
+    #[error("registry denied access: {0}")]
+    RegistryDenied(String),
     /// An error from a message.
     #[error("{0}")]
     Message(String),
 }
 
+impl Error {
+    /// Gets the underlying [`bollard`] error, if there is one.
+    pub fn docker_error(&self) -> Option<&bollard::errors::Error> {
+        match self {
+            Self::Docker(e)
+            | Self::NotFound(e)
+            | Self::Conflict(e)
+            | Self::BadRequest(e)
+            | Self::ServerError(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Gets the status code the Docker daemon responded with, if the error is
+    /// from a response of the Docker daemon.
+    pub fn status_code(&self) -> Option<u16> {
+        match self.docker_error()? {
+            bollard::errors::Error::DockerResponseServerError { status_code, .. } => {
+                Some(*status_code)
+            }
+            _ => None,
+        }
+    }
+
+    /// Gets a suggestion for how to fix the cause of the error, if it is a
+    /// common failure.
+    ///
+    /// The suggestions are meant to be shown to users alongside the error,
+    /// such as by a command line tool. They cover the Docker daemon not
+    /// running or its socket not being accessible, images that do not exist
+    /// or require authentication, registry rate limits, running out of disk
+    /// space, and container name conflicts.
+    pub fn user_hint(&self) -> Option<&'static str> {
+        match self.io_error_kind() {
+            Some(std::io::ErrorKind::PermissionDenied) => {
+                return Some(
+                    "make sure you have permission to access the Docker socket, such as by adding \
+                     your user to the `docker` group (and logging in again)",
+                );
+            }
+            Some(std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused) => {
+                return Some(
+                    "make sure the Docker daemon is running and that `DOCKER_HOST` points to it",
+                );
+            }
+            _ => {}
+        }
+
+        match self {
+            Self::Docker(bollard::errors::Error::SocketNotFoundError(_)) => {
+                return Some(
+                    "make sure the Docker daemon is running and that `DOCKER_HOST` points to it",
+                );
+            }
+            Self::NameConflict { .. } => {
+                return Some(
+                    "remove the existing container (e.g., with `docker rm -f`) or use another name",
+                );
+            }
+            Self::RegistryUnauthorized(_) => {
+                return Some("log in to the registry with `docker login` or provide credentials");
+            }
+            _ => {}
+        }
+
+        let message = self.to_string().to_lowercase();
+        if message.contains("no space left on device") {
+            Some(
+                "free up disk space, such as by removing unused images and containers with \
+                 `docker system prune`",
+            )
+        } else if message.contains("toomanyrequests") || message.contains("rate limit") {
+            Some("wait before pulling again, or log in to the registry to raise the rate limit")
+        } else if matches!(self, Self::RegistryDenied(_))
+            || message.contains("manifest unknown")
+            || message.contains("repository does not exist")
+            || (matches!(self, Self::NotFound(_)) && message.contains("no such image"))
+        {
+            Some(
+                "check that the image name and tag are correct and, for a private image, log in \
+                 to its registry with `docker login`",
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Gets the kind of the I/O error that caused the error, if there is one.
+    fn io_error_kind(&self) -> Option<std::io::ErrorKind> {
+        // Transparent errors forward their source rather than being their own
+        // source, so the I/O error of `IOError` must be read directly
+        let mut source: Option<&(dyn std::error::Error + 'static)> = match self.docker_error()? {
+            bollard::errors::Error::IOError { err } => return Some(err.kind()),
+            e => Some(e),
+        };
+
+        while let Some(e) = source {
+            if let Some(e) = e.downcast_ref::<std::io::Error>() {
+                return Some(e.kind());
+            }
+
+            source = e.source();
+        }
+
+        None
+    }
+}
+
+impl From<bollard::errors::Error> for Error {
+    fn from(e: bollard::errors::Error) -> Self {
+        match e {
+            bollard::errors::Error::DockerResponseServerError {
+                status_code: 404, ..
+            } => Self::NotFound(e),
+            bollard::errors::Error::DockerResponseServerError {
+                status_code: 409, ..
+            } => Self::Conflict(e),
+            bollard::errors::Error::DockerResponseServerError {
+                status_code: 400, ..
+            } => Self::BadRequest(e),
+            bollard::errors::Error::DockerResponseServerError {
+                status_code: 500, ..
+            } => Self::ServerError(e),
+            e => Self::Docker(e),
+        }
+    }
+}
+
 /// A [`Result`](std::result::Result) with an [`Error`].
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// The timeout of requests to the Docker daemon, which matches the default
+/// used by [`bollard`].
+#[cfg(unix)]
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+
+/// The path of the default Docker socket.
+#[cfg(unix)]
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+/// The default address of the Docker daemon over HTTP, which matches the
+/// default used by [`bollard`].
+const DEFAULT_HTTP_HOST: &str = "tcp://localhost:2375";
+
+/// The path of the socket of a rootful Podman service.
+#[cfg(unix)]
+const ROOTFUL_PODMAN_SOCKET: &str = "/run/podman/podman.sock";
+
+/// Gets the Podman socket to connect to when the Docker socket does not exist.
+///
+/// `exists` is used to check whether a socket exists, and `runtime_dir` is the
+/// value of `XDG_RUNTIME_DIR`, under which a rootless Podman service listens.
+#[cfg(unix)]
+fn podman_socket(
+    runtime_dir: Option<&std::ffi::OsStr>,
+    exists: impl Fn(&std::path::Path) -> bool,
+) -> Option<std::path::PathBuf> {
+    if exists(std::path::Path::new(DOCKER_SOCKET)) {
+        return None;
+    }
+
+    runtime_dir
+        .map(|dir| std::path::Path::new(dir).join("podman").join("podman.sock"))
+        .into_iter()
+        .chain([std::path::PathBuf::from(ROOTFUL_PODMAN_SOCKET)])
+        .find(|socket| exists(socket))
+}
+
 /// A Docker client.
+///
+/// Cloning a client is cheap and all clones share the same underlying
+/// connection to the Docker daemon: the [`bollard::Docker`] within holds its
+/// HTTP client behind an [`Arc`](std::sync::Arc), so every [`Container`]
+/// created from a client (each of which keeps a clone of it) uses the same
+/// connection pool. Create one client and clone it rather than connecting
+/// again for each container.
+///
+/// The pool does not keep idle connections: a connection is opened for each
+/// request and closed when the request completes. Connections therefore only
+/// grow with the number of requests in flight, not with the number of
+/// containers, but long-lived requests (such as waiting on, attaching to, or
+/// following the logs of a container) each hold a connection for as long as
+/// they run. When running thousands of containers at once, limit how many are
+/// run concurrently (for example, with a [`Semaphore`](tokio::sync::Semaphore))
+/// to bound the number of open connections. The pool does not expose metrics
+/// of its connections.
 #[derive(Clone, Debug)]
-pub struct Docker(bollard::Docker);
+pub struct Docker {
+    /// The client to use for Docker operations.
+    client: bollard::Docker,
+
+    /// The address of the Docker daemon, if it is known, for the operations
+    /// that [`bollard`] does not support.
+    address: Option<DaemonAddress>,
+}
 
 impl Docker {
     /// Creates a new [`Docker`] with the specified [client](bollard::Docker).
+    ///
+    /// The address of the Docker daemon cannot be read from the client, so
+    /// operations that require it, such as [`Container::checkpoint()`], are
+    /// not supported; create the client with [`Self::with_defaults()`] or a
+    /// similar constructor instead to use them.
     pub fn new(client: bollard::Docker) -> Self {
-        Self(client)
+        Self {
+            client,
+            address: None,
+        }
     }
 
     /// Attempts to create a new [`Docker`] with the default socket connection.
     pub fn with_socket_defaults() -> Result<Self> {
-        let client = bollard::Docker::connect_with_socket_defaults().map_err(Error::Docker)?;
-        Ok(Self::new(client))
+        let client = bollard::Docker::connect_with_socket_defaults().map_err(Error::from)?;
+
+        #[cfg(unix)]
+        let address = Some(DaemonAddress::Unix(DOCKER_SOCKET.into()));
+        #[cfg(not(unix))]
+        let address = None;
+
+        Ok(Self::with_address(client, address))
     }
 
     /// Attempts to create a new [`Docker`] with the default HTTP connection.
     pub fn with_http_defaults() -> Result<Self> {
-        let client = bollard::Docker::connect_with_http_defaults().map_err(Error::Docker)?;
-        Ok(Self::new(client))
+        let client = bollard::Docker::connect_with_http_defaults().map_err(Error::from)?;
+        let host = std::env::var("DOCKER_HOST").unwrap_or_else(|_| DEFAULT_HTTP_HOST.to_string());
+        Ok(Self::with_address(
+            client,
+            DaemonAddress::from_host(&host, false),
+        ))
+    }
+
+    /// Creates a new [`Docker`] with a client and the address of the Docker
+    /// daemon it connects to.
+    fn with_address(client: bollard::Docker, address: Option<DaemonAddress>) -> Self {
+        Self { client, address }
     }
 
     /// Attempts to create a new [`Docker`] with the default connection details.
+    ///
+    /// The connection is chosen as follows:
+    ///
+    /// 1. If the `DOCKER_HOST` environment variable is set, it is used.
+    /// 2. Otherwise, on Unix, the Docker socket (`/var/run/docker.sock`) is
+    ///    used if it exists.
+    /// 3. Otherwise, on Unix, the first Podman socket that exists is used:
+    ///    the rootless socket (`$XDG_RUNTIME_DIR/podman/podman.sock`), then the
+    ///    rootful socket (`/run/podman/podman.sock`).
+    /// 4. Otherwise, the platform's default Docker connection is used.
+    ///
+    /// Podman serves a Docker-compatible API, but it is not identical to the
+    /// Docker daemon's. Notably, swarm features such as [`Self::nodes()`] and
+    /// services are not supported, the API version it reports differs from
+    /// Docker's (so [`Self::negotiate_version()`] is recommended), and rootless
+    /// Podman cannot use options that require root on the host, such as
+    /// publishing ports below 1024 or setting some resource limits.
     pub fn with_defaults() -> Result<Self> {
-        let client = bollard::Docker::connect_with_defaults().map_err(Error::Docker)?;
-        Ok(Self::new(client))
+        #[cfg(unix)]
+        if std::env::var_os("DOCKER_HOST").is_none() {
+            let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR");
+            if let Some(socket) = podman_socket(runtime_dir.as_deref(), |p| p.exists()) {
+                debug!(
+                    "connecting to the Podman socket `{socket}` as the Docker socket does not \
+                     exist",
+                    socket = socket.display()
+                );
+
+                let client = bollard::Docker::connect_with_socket(
+                    &socket.to_string_lossy(),
+                    DEFAULT_TIMEOUT_SECS,
+                    bollard::API_DEFAULT_VERSION,
+                )
+                .map_err(Error::from)?;
+                return Ok(Self::with_address(
+                    client,
+                    Some(DaemonAddress::Unix(socket)),
+                ));
+            }
+        }
+
+        let client = bollard::Docker::connect_with_defaults().map_err(Error::from)?;
+        let address = match std::env::var("DOCKER_HOST") {
+            Ok(host) => {
+                DaemonAddress::from_host(&host, std::env::var_os("DOCKER_TLS_VERIFY").is_some())
+            }
+            #[cfg(unix)]
+            Err(_) => Some(DaemonAddress::Unix(DOCKER_SOCKET.into())),
+            #[cfg(not(unix))]
+            Err(_) => None,
+        };
+
+        Ok(Self::with_address(client, address))
+    }
+
+    /// Negotiates the API version with the Docker daemon.
+    ///
+    /// If the daemon supports an older version of the API than the client, the
+    /// client is downgraded to the daemon's version; this avoids "client
+    /// version is too new" errors when connecting to older daemons. The
+    /// negotiated version can be read with [`Self::api_version()`].
+    pub async fn negotiate_version(self) -> Result<Self> {
+        let client = self.client.negotiate_version().await.map_err(Error::from)?;
+        Ok(Self::with_address(client, self.address))
+    }
+
+    /// Gets the version of the Docker API used by the client (e.g., `1.49`).
+    pub fn api_version(&self) -> String {
+        self.client.client_version().to_string()
     }
 
     /// Gets a reference to the inner [`bollard::Docker`].
     pub fn inner(&self) -> &bollard::Docker {
-        &self.0
+        &self.client
     }
 
     //----------------------------------------------------------------------------------
@@ -84,7 +465,170 @@ impl Docker {
     /// * Confirming that the image already exists there, or
     /// * Pulling the image from the remote repository.
     pub async fn ensure_image(&self, image: impl AsRef<str>) -> Result<()> {
-        ensure_image(self, image).await
+        ensure_image(self, image, None, &PullRetry::new().max_attempts(1)).await
+    }
+
+    /// Ensures that an image exists in the Docker daemon, pulling it with the
+    /// given registry credentials if needed.
+    ///
+    /// See [`RegistryAuth::from_docker_config()`] to use the credentials from
+    /// the user's Docker configuration.
+    pub async fn ensure_image_with_auth(
+        &self,
+        image: impl AsRef<str>,
+        auth: Option<&RegistryAuth>,
+    ) -> Result<()> {
+        ensure_image(self, image, auth, &PullRetry::new().max_attempts(1)).await
+    }
+
+    /// Ensures that an image exists in the Docker daemon, pulling it with the
+    /// given registry credentials if needed and retrying the pull if it fails
+    /// with a transient error.
+    ///
+    /// See [`PullRetry`] for which errors are retried.
+    pub async fn ensure_image_with_retry(
+        &self,
+        image: impl AsRef<str>,
+        auth: Option<&RegistryAuth>,
+        retry: &PullRetry,
+    ) -> Result<()> {
+        ensure_image(self, image, auth, retry).await
+    }
+
+    /// Pulls an image for a platform, even if it already exists locally.
+    ///
+    /// The platform is given as `os/arch[/variant]` (e.g., `linux/arm64`);
+    /// without one, the platform of the Docker daemon is used. Returns the ID
+    /// of the pulled image and the digest its reference resolved to, so that
+    /// deployments can record exactly which image was pulled. See
+    /// [`PulledImage`] for what the digest refers to for multi-platform
+    /// images.
+    ///
+    /// Like [`Self::ensure_image_with_retry()`], the pull is retried if it
+    /// fails with a transient error; see [`PullRetry`].
+    pub async fn pull_image(
+        &self,
+        image: impl AsRef<str>,
+        platform: Option<&str>,
+        auth: Option<&RegistryAuth>,
+        retry: &PullRetry,
+    ) -> Result<PulledImage> {
+        pull_image_for_platform(self, image.as_ref(), platform, auth, retry).await
+    }
+
+    /// Inspects an image in the Docker daemon.
+    ///
+    /// See [`images::ImageDefaults`] for a more convenient view of the image's
+    /// default entrypoint, command, environment, and exposed ports.
+    pub async fn inspect_image(&self, image: impl AsRef<str>) -> Result<ImageInspect> {
+        inspect_image(self, image).await
+    }
+
+    /// Gets the defaults an image uses when running a container.
+    pub async fn image_defaults(&self, image: impl AsRef<str>) -> Result<ImageDefaults> {
+        inspect_image(self, image).await.map(Into::into)
+    }
+
+    /// Checks whether a command exists within an image, for validating a
+    /// command before a container is run with it.
+    ///
+    /// A command containing a `/` is checked as a path (relative paths are
+    /// resolved against the image's working directory); otherwise, it is
+    /// searched for within each directory of the image's `PATH`, as a shell
+    /// would. The command must be an executable file or a symbolic link
+    /// (which is not followed, so a broken link is still reported as found).
+    ///
+    /// The image is pulled if it does not exist locally. To check the image,
+    /// a container is created from it but never started, so this also works
+    /// for images without a shell; the container is removed afterwards.
+    pub async fn has_command(&self, image: impl AsRef<str>, cmd: impl AsRef<str>) -> Result<bool> {
+        has_command(self, image, cmd).await
+    }
+
+    /// Tags an image in the Docker daemon.
+    ///
+    /// If `target` does not specify a tag, a default tag of `latest` will be
+    /// used.
+    pub async fn tag_image(&self, source: impl AsRef<str>, target: impl AsRef<str>) -> Result<()> {
+        tag_image(self, source, target).await
+    }
+
+    /// Pushes an image from the Docker daemon to its registry.
+    ///
+    /// If the image does not specify a tag, a default tag of `latest` will be
+    /// used.
+    ///
+    /// The returned stream yields the progress updates of the push and must be
+    /// polled to completion for the push to finish.
+    pub fn push_image<T: AsRef<str>>(
+        &self,
+        image: T,
+        auth: Option<&RegistryAuth>,
+    ) -> impl Stream<Item = Result<PushImageInfo>> + use<T> {
+        push_image(self, image, auth)
+    }
+
+    /// Builds an image from a build context given as a tar archive.
+    ///
+    /// The archive contains the Dockerfile and any files it references.
+    /// Returns the ID of the built image.
+    ///
+    /// See [`Self::build_image_from_reader()`] to stream the archive instead
+    /// of holding it in memory.
+    pub async fn build_image(&self, context: Vec<u8>, options: BuildOptions) -> Result<String> {
+        build_image(self, context, options).await
+    }
+
+    /// Builds an image from a build context streamed as a tar archive.
+    ///
+    /// The archive is streamed to the Docker daemon as it is read, so large
+    /// contexts are not held in memory. A context can be streamed from a
+    /// directory with [`images::build_context_tar()`], which honors
+    /// `.dockerignore`. Returns the ID of the built image.
+    ///
+    /// As the archive can only be read once, a BuildKit build (see
+    /// [`BuildOptions::buildkit()`]) that the Docker daemon fails to start
+    /// returns the error rather than falling back to the classic builder.
+    pub async fn build_image_from_reader(
+        &self,
+        context: impl AsyncRead + Send + 'static,
+        options: BuildOptions,
+    ) -> Result<String> {
+        build_image_from_reader(self, context, options).await
+    }
+
+    /// Loads the images in a tar archive (as created by `docker save`) into the
+    /// Docker daemon.
+    ///
+    /// The archive is streamed to the daemon as it is read. Returns the
+    /// references of the loaded images; images without a tag are referenced
+    /// by their ID.
+    pub async fn load_image(
+        &self,
+        archive: impl AsyncRead + Send + 'static,
+    ) -> Result<Vec<String>> {
+        load_image(self, archive).await
+    }
+
+    /// Saves an image from the Docker daemon as a tar archive.
+    ///
+    /// The returned stream yields the contents of the archive, which can be
+    /// loaded with [`Self::load_image()`].
+    pub fn save_image<T: AsRef<str>>(
+        &self,
+        image: T,
+    ) -> impl Stream<Item = Result<Bytes>> + use<T> {
+        save_image(self, image)
+    }
+
+    /// Saves an image from the Docker daemon as a tar archive written to the
+    /// given writer.
+    pub async fn save_image_to(
+        &self,
+        image: impl AsRef<str>,
+        writer: impl AsyncWrite + Unpin,
+    ) -> Result<()> {
+        save_image_to(self, image, writer).await
     }
 
     /// Removes an image from the Docker daemon.
@@ -109,7 +653,7 @@ impl Docker {
     ///
     /// This is the typical way you will create containers.
     pub fn container_builder(&self) -> container::Builder {
-        container::Builder::new(self.0.clone())
+        container::Builder::new(self.client.clone()).daemon_address(self.address.clone())
     }
 
     /// Creates a container from a known id.
@@ -123,7 +667,188 @@ impl Docker {
         attach_stdout: bool,
         attach_stderr: bool,
     ) -> Container {
-        Container::new(self.0.clone(), id.into(), attach_stdout, attach_stderr)
+        Container::new(self.client.clone(), id.into(), attach_stdout, attach_stderr)
+            .with_daemon_address(self.address.clone())
+    }
+
+    /// Finds the running containers whose names match a glob (e.g., `web-*`),
+    /// sorted by name.
+    ///
+    /// In the glob, `*` matches any sequence of characters and `?` matches any
+    /// single character. Each container is inspected to find which of its
+    /// streams are attached, so the returned handles can be attached to or
+    /// have their logs followed just like the containers this crate creates.
+    pub async fn find_containers(&self, name_glob: &str) -> Result<Vec<Container>> {
+        Ok(container::find_containers(&self.client, name_glob)
+            .await?
+            .into_iter()
+            .map(|container| container.with_daemon_address(self.address.clone()))
+            .collect())
+    }
+
+    /// Runs a command in a new container and returns its standard output.
+    ///
+    /// The first element of `cmd` is the program to run and the rest are its
+    /// arguments. The image is pulled if it does not exist locally, and the
+    /// container is removed once the command has finished, even if it fails.
+    ///
+    /// [`Error::NonZeroExit`] is returned if the command exits with a non-zero
+    /// exit code, and an error is also returned if its standard output is not
+    /// valid UTF-8.
+    pub async fn run_once(
+        &self,
+        image: impl AsRef<str>,
+        cmd: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<String> {
+        let image = image.as_ref();
+        self.ensure_image(image).await?;
+
+        let mut cmd = cmd.into_iter();
+        let mut builder = self
+            .container_builder()
+            .image(image)
+            .attach_stdout()
+            .attach_stderr();
+        if let Some(program) = cmd.next() {
+            builder = builder.program(program).args(cmd);
+        }
+
+        // An empty name has the Docker daemon generate a unique one; the guard
+        // removes the container should this future be dropped early
+        let container = builder.try_build("").await?.guard();
+        let output = container.run(|| {}).await;
+
+        let container = container.into_inner();
+        if let Err(e) = container.force_remove().await {
+            warn!("failed to remove container after running a command: {e}");
+        }
+
+        let output = output?;
+        container::check_exit(&output)?;
+
+        String::from_utf8(output.stdout)
+            .map_err(|_| Error::Message(String::from("command output is not valid UTF-8")))
+    }
+
+    /// Runs a batch job in a new container.
+    ///
+    /// This creates a container from the image of the job, uploads the job's
+    /// inputs, runs the container, and downloads the job's outputs once it has
+    /// exited successfully. The container is removed afterwards regardless of
+    /// whether any step failed, including if the returned future is dropped
+    /// early.
+    ///
+    /// [`Error::NonZeroExit`] is returned if the job exits with a non-zero exit
+    /// code, in which case no outputs are downloaded.
+    pub async fn run_job(&self, spec: container::JobSpec) -> Result<container::JobResult> {
+        container::run_job(self, spec).await
+    }
+
+    /// Runs a pipeline of containers that share a scratch volume.
+    ///
+    /// This creates a volume, then runs the stages in order, each in a new
+    /// container with the volume mounted at the stage's
+    /// [`volume_path`](container::Stage::volume_path), so a stage can read
+    /// what the stages before it wrote. Each container is removed once its
+    /// stage has finished, and the volume is removed once the pipeline has
+    /// finished, regardless of whether any stage failed, including if the
+    /// returned future is dropped early.
+    ///
+    /// [`Error::InvalidBuilderField`] is returned before anything is created if
+    /// the volume path of any stage is not an absolute path.
+    ///
+    /// Returns the exit status and collected output of each stage. The
+    /// pipeline stops at the first stage that exits with a non-zero exit code,
+    /// for which [`Error::NonZeroExit`] is returned.
+    pub async fn run_pipeline(&self, stages: Vec<container::Stage>) -> Result<Vec<Output>> {
+        container::run_pipeline(self, stages).await
+    }
+
+    /// Force removes all containers, running or not, with the given label.
+    ///
+    /// This is useful for cleaning up containers left behind by a previous run
+    /// that exited uncleanly, when containers are created with
+    /// [`container::Builder::label()`]. A failure to remove one container does
+    /// not prevent the others from being removed; such failures are collected
+    /// in the returned report.
+    pub async fn reap_by_label(
+        &self,
+        key: impl AsRef<str>,
+        value: impl AsRef<str>,
+    ) -> Result<container::ReapReport> {
+        container::reap_by_label(&self.client, key.as_ref(), value.as_ref()).await
+    }
+
+    //----------------------------------------------------------------------------------
+    // Events
+    //----------------------------------------------------------------------------------
+
+    /// Watches for containers that restart more than `threshold` times within
+    /// `window`.
+    ///
+    /// A container restarts when it is started after it has died, such as by
+    /// its restart policy. Each time a container exceeds the threshold, a
+    /// [`events::RestartLoop`] is yielded and its count is reset, so a
+    /// container that keeps restarting is reported again each time it
+    /// exceeds the threshold anew. Only events that occur after the stream is
+    /// first polled are seen.
+    pub fn watch_restart_loops(
+        &self,
+        threshold: usize,
+        window: std::time::Duration,
+    ) -> impl Stream<Item = Result<events::RestartLoop>> + use<> {
+        events::watch_restart_loops(self, threshold, window)
+    }
+
+    /// Streams events from the Docker daemon, reconnecting whenever the
+    /// stream is dropped (for example, because the Docker daemon restarted).
+    ///
+    /// Reconnections are retried indefinitely with a jittered, capped
+    /// exponential backoff and are only logged; errors that would recur on
+    /// reconnecting (such as invalid filters) are yielded and end the stream.
+    /// See [`reconnect::ReconnectOptions`] for how reconnected streams resume.
+    ///
+    /// The stream only ends on its own if `options` has an end time.
+    pub fn events_reconnecting(
+        &self,
+        options: bollard::query_parameters::EventsOptions,
+        reconnect: reconnect::ReconnectOptions,
+    ) -> impl Stream<Item = Result<bollard::secret::EventMessage>> + use<> {
+        reconnect::events(self.client.clone(), options, reconnect)
+    }
+
+    //----------------------------------------------------------------------------------
+    // Networks
+    //----------------------------------------------------------------------------------
+
+    /// Gets the networks matching the given filters.
+    ///
+    /// The filters are those of the Docker Engine API (e.g., a `label` filter
+    /// of `app=web` for the networks with that label); empty filters match all
+    /// networks. Each summary includes whether the network is in use by any
+    /// container, which is useful for finding orphaned networks to remove.
+    pub async fn list_networks(
+        &self,
+        filters: HashMap<String, Vec<String>>,
+    ) -> Result<Vec<networks::NetworkSummary>> {
+        networks::list_networks(&self.client, filters).await
+    }
+
+    //----------------------------------------------------------------------------------
+    // Volumes
+    //----------------------------------------------------------------------------------
+
+    /// Gets the volumes matching the given filters.
+    ///
+    /// The filters are those of the Docker Engine API (e.g., a `label` filter
+    /// of `app=web` for the volumes with that label); empty filters match all
+    /// volumes. Each summary includes whether the volume is mounted into any
+    /// container, which is useful for finding orphaned volumes to remove.
+    pub async fn list_volumes(
+        &self,
+        filters: HashMap<String, Vec<String>>,
+    ) -> Result<Vec<volumes::VolumeSummary>> {
+        volumes::list_volumes(&self.client, filters).await
     }
 
     //----------------------------------------------------------------------------------
@@ -135,7 +860,7 @@ impl Docker {
     /// This method should only be called for a Docker daemon that has been
     /// joined to a swarm.
     pub async fn nodes(&self) -> Result<Vec<Node>> {
-        self.0
+        self.client
             .list_nodes(None::<ListNodesOptions>)
             .await
             .map_err(Into::into)
@@ -149,7 +874,7 @@ impl Docker {
     ///
     /// This is the typical way you will create services.
     pub fn service_builder(&self) -> service::Builder {
-        service::Builder::new(self.0.clone())
+        service::Builder::new(self.client.clone())
     }
 
     //----------------------------------------------------------------------------------
@@ -158,9 +883,192 @@ impl Docker {
 
     /// Gets the system information.
     pub async fn info(&self) -> Result<SystemInfo> {
-        self.0.info().await.map_err(Into::into)
+        self.client.info().await.map_err(Into::into)
+    }
+
+    /// Gets whether the Docker daemon has experimental features enabled.
+    ///
+    /// Some features of the daemon are only available when experimental
+    /// features are enabled, such as checkpointing and restoring containers
+    /// with [`Container::checkpoint()`] and [`Container::restore()`].
+    pub async fn experimental(&self) -> Result<bool> {
+        Ok(self
+            .client
+            .version()
+            .await?
+            .experimental
+            .unwrap_or_default())
+    }
+
+    /// Gets whether the Docker daemon is running in rootless mode.
+    ///
+    /// A rootless daemon cannot grant containers more privileges than the user
+    /// running it has, so options such as privileged containers or binding
+    /// ports below 1024 on the host may fail or behave differently. Checking
+    /// this upfront allows such options to be avoided or reported clearly.
+    ///
+    /// The mode is read from the security options reported in the daemon's
+    /// system information.
+    pub async fn is_rootless(&self) -> Result<bool> {
+        let info = self.info().await?;
+        Ok(has_security_option(
+            info.security_options.as_deref().unwrap_or_default(),
+            "rootless",
+        ))
     }
 }
 
+/// Checks whether the security options reported by the Docker daemon (e.g.,
+/// `name=seccomp,profile=builtin`) include an option with the given name.
+pub(crate) fn has_security_option(security_options: &[String], name: &str) -> bool {
+    security_options.iter().any(|option| {
+        option
+            .split(',')
+            .any(|field| field.strip_prefix("name=") == Some(name))
+    })
+}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn podman_sockets() {
+        use std::path::Path;
+
+        let runtime_dir = std::ffi::OsStr::new("/run/user/1000");
+        let rootless = Path::new("/run/user/1000/podman/podman.sock");
+
+        assert_eq!(podman_socket(Some(runtime_dir), |_| true), None);
+        assert_eq!(
+            podman_socket(Some(runtime_dir), |p| p != Path::new(DOCKER_SOCKET)).as_deref(),
+            Some(rootless)
+        );
+        assert_eq!(
+            podman_socket(Some(runtime_dir), |p| p == Path::new(ROOTFUL_PODMAN_SOCKET)).as_deref(),
+            Some(Path::new(ROOTFUL_PODMAN_SOCKET))
+        );
+        assert_eq!(podman_socket(None, |p| p == rootless), None);
+    }
+
+    #[test]
+    fn user_hints() {
+        let io = |kind| {
+            Error::from(bollard::errors::Error::IOError {
+                err: std::io::Error::from(kind),
+            })
+        };
+        let response = |status_code, message: &str| {
+            Error::from(bollard::errors::Error::DockerResponseServerError {
+                status_code,
+                message: message.to_string(),
+            })
+        };
+
+        assert!(
+            io(std::io::ErrorKind::PermissionDenied)
+                .user_hint()
+                .unwrap()
+                .contains("permission")
+        );
+        assert!(
+            io(std::io::ErrorKind::ConnectionRefused)
+                .user_hint()
+                .unwrap()
+                .contains("daemon is running")
+        );
+        assert!(
+            Error::from(bollard::errors::Error::SocketNotFoundError(String::from(
+                "/var/run/docker.sock"
+            )))
+            .user_hint()
+            .unwrap()
+            .contains("daemon is running")
+        );
+        assert!(
+            response(404, "No such image: app:latest")
+                .user_hint()
+                .unwrap()
+                .contains("image name")
+        );
+        assert!(
+            Error::RegistryDenied(String::from("pull access denied for app"))
+                .user_hint()
+                .unwrap()
+                .contains("image name")
+        );
+        assert!(
+            response(500, "write /var/lib/docker/tmp: no space left on device")
+                .user_hint()
+                .unwrap()
+                .contains("disk space")
+        );
+        assert!(
+            Error::Message(String::from(
+                "toomanyrequests: You have reached your pull rate limit"
+            ))
+            .user_hint()
+            .unwrap()
+            .contains("rate limit")
+        );
+        assert!(
+            Error::NameConflict {
+                name: String::from("app")
+            }
+            .user_hint()
+            .is_some()
+        );
+        assert!(
+            response(404, "No such container: app")
+                .user_hint()
+                .is_none()
+        );
+        assert!(io(std::io::ErrorKind::InvalidData).user_hint().is_none());
+    }
+
+    #[test]
+    fn security_options() {
+        let options = [
+            String::from("name=seccomp,profile=builtin"),
+            String::from("name=rootless"),
+            String::from("name=cgroupns"),
+        ];
+
+        assert!(has_security_option(&options, "rootless"));
+        assert!(has_security_option(&options, "seccomp"));
+        assert!(!has_security_option(&options, "selinux"));
+        assert!(!has_security_option(&options, "builtin"));
+        assert!(!has_security_option(&[], "rootless"));
+    }
+
+    fn response_error(status_code: u16) -> bollard::errors::Error {
+        bollard::errors::Error::DockerResponseServerError {
+            status_code,
+            message: String::from("message"),
+        }
+    }
+
+    #[test]
+    fn status_codes() {
+        assert!(matches!(
+            Error::from(response_error(404)),
+            Error::NotFound(_)
+        ));
+        assert!(matches!(
+            Error::from(response_error(409)),
+            Error::Conflict(_)
+        ));
+        assert!(matches!(
+            Error::from(response_error(400)),
+            Error::BadRequest(_)
+        ));
+        assert!(matches!(
+            Error::from(response_error(500)),
+            Error::ServerError(_)
+        ));
+        assert!(matches!(Error::from(response_error(503)), Error::Docker(_)));
+        assert_eq!(Error::from(response_error(409)).status_code(), Some(409));
+        assert_eq!(Error::Message(String::from("message")).status_code(), None);
+    }
+}
@@ -0,0 +1,325 @@
+//! Container log output.
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use bollard::container::LogOutput;
+use bytes::Bytes;
+use futures::Stream;
+use tokio_stream::StreamExt as _;
+
+use crate::Result;
+
+/// An output stream of a container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LogStream {
+    /// Standard output.
+    ///
+    /// When a container has a TTY, all of its output is on this stream.
+    Stdout,
+    /// Standard error.
+    Stderr,
+}
+
+/// A complete line of output from a container.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    /// The stream the line was written to.
+    pub stream: LogStream,
+
+    /// The text of the line without the line ending.
+    ///
+    /// Any invalid UTF-8 is replaced with `U+FFFD REPLACEMENT CHARACTER`.
+    pub text: String,
+}
+
+/// A frame of output from a container, as received from the Docker daemon.
+///
+/// Unlike output collected into separate buffers for each stream, a stream of
+/// frames preserves the order in which output was received across standard
+/// output and standard error. This is the order in which the Docker daemon
+/// read the output from the container, which matches the order it was written
+/// in unless the container's process writes to both streams in quick
+/// succession.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// The stream the output was written to.
+    pub stream: LogStream,
+
+    /// The output.
+    pub data: Bytes,
+
+    /// The position of the frame within the stream of frames, starting at
+    /// zero.
+    pub index: u64,
+
+    /// When the frame was received.
+    pub received: Instant,
+}
+
+impl Frame {
+    /// Converts a stream of container output into a stream of frames in the
+    /// order they are received.
+    ///
+    /// Output written to standard input is skipped.
+    pub fn frames(
+        stream: impl Stream<Item = Result<LogOutput>>,
+    ) -> impl Stream<Item = Result<Frame>> {
+        let mut index = 0;
+        stream.filter_map(move |output| {
+            let received = Instant::now();
+            let (stream, data) = match output {
+                Ok(LogOutput::StdOut { message } | LogOutput::Console { message }) => {
+                    (LogStream::Stdout, message)
+                }
+                Ok(LogOutput::StdErr { message }) => (LogStream::Stderr, message),
+                Ok(LogOutput::StdIn { .. }) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let frame = Frame {
+                stream,
+                data,
+                index,
+                received,
+            };
+            index += 1;
+            Some(Ok(frame))
+        })
+    }
+}
+
+/// Decodes container output into complete lines.
+///
+/// The chunks of output received from the Docker daemon do not align to line
+/// boundaries; the decoder buffers each stream separately until a line is
+/// complete. As a newline byte never appears within a multibyte UTF-8
+/// sequence, characters split across chunks are decoded correctly.
+///
+/// Both `\n` and `\r\n` are treated as line endings.
+#[derive(Debug, Default)]
+pub struct LineDecoder {
+    /// The incomplete line of standard output.
+    stdout: Vec<u8>,
+
+    /// The incomplete line of standard error.
+    stderr: Vec<u8>,
+}
+
+impl LineDecoder {
+    /// Creates a new [`LineDecoder`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes a chunk of output, returning any lines it completes.
+    ///
+    /// Output written to standard input is ignored.
+    pub fn decode(&mut self, output: LogOutput) -> Vec<Line> {
+        let (stream, message) = match output {
+            LogOutput::StdOut { message } | LogOutput::Console { message } => {
+                (LogStream::Stdout, message)
+            }
+            LogOutput::StdErr { message } => (LogStream::Stderr, message),
+            LogOutput::StdIn { .. } => return Vec::new(),
+        };
+
+        let buffer = self.buffer(stream);
+        buffer.extend_from_slice(&message);
+
+        let mut lines = Vec::new();
+        let mut start = 0;
+        while let Some(end) = buffer[start..].iter().position(|b| *b == b'\n') {
+            lines.push(to_line(stream, &buffer[start..start + end]));
+            start += end + 1;
+        }
+
+        buffer.drain(..start);
+        lines
+    }
+
+    /// Finishes decoding, returning any incomplete lines that remain.
+    ///
+    /// Standard output is returned before standard error.
+    pub fn finish(&mut self) -> Vec<Line> {
+        [LogStream::Stdout, LogStream::Stderr]
+            .into_iter()
+            .filter_map(|stream| {
+                let buffer = std::mem::take(self.buffer(stream));
+                (!buffer.is_empty()).then(|| to_line(stream, &buffer))
+            })
+            .collect()
+    }
+
+    /// Decodes a stream of container output into a stream of lines.
+    ///
+    /// Any incomplete lines are returned when the stream ends. The stream ends
+    /// after the first error.
+    pub fn lines(
+        stream: impl Stream<Item = Result<LogOutput>>,
+    ) -> impl Stream<Item = Result<Line>> {
+        futures::stream::unfold(
+            (Box::pin(stream), Self::new(), VecDeque::new(), false),
+            |(mut stream, mut decoder, mut pending, mut done)| async move {
+                loop {
+                    if let Some(line) = pending.pop_front() {
+                        return Some((Ok(line), (stream, decoder, pending, done)));
+                    }
+
+                    if done {
+                        return None;
+                    }
+
+                    match stream.next().await {
+                        Some(Ok(output)) => pending.extend(decoder.decode(output)),
+                        Some(Err(e)) => {
+                            return Some((Err(e), (stream, decoder, pending, true)));
+                        }
+                        None => {
+                            pending.extend(decoder.finish());
+                            done = true;
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    /// Gets the buffer for the given stream.
+    fn buffer(&mut self, stream: LogStream) -> &mut Vec<u8> {
+        match stream {
+            LogStream::Stdout => &mut self.stdout,
+            LogStream::Stderr => &mut self.stderr,
+        }
+    }
+}
+
+/// Converts the bytes of a line, without its `\n`, to a [`Line`].
+fn to_line(stream: LogStream, bytes: &[u8]) -> Line {
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+
+    Line {
+        stream,
+        text: String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stdout(message: &[u8]) -> LogOutput {
+        LogOutput::StdOut {
+            message: message.to_vec().into(),
+        }
+    }
+
+    fn stderr(message: &[u8]) -> LogOutput {
+        LogOutput::StdErr {
+            message: message.to_vec().into(),
+        }
+    }
+
+    fn line(stream: LogStream, text: &str) -> Line {
+        Line {
+            stream,
+            text: text.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn frames() {
+        let outputs = [
+            Ok(stdout(b"a")),
+            Ok(LogOutput::StdIn {
+                message: Bytes::from_static(b"in"),
+            }),
+            Ok(stderr(b"b")),
+            Ok(stdout(b"c")),
+        ];
+        let frames = Frame::frames(tokio_stream::iter(outputs))
+            .collect::<Result<Vec<_>>>()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            frames
+                .iter()
+                .map(|frame| (frame.stream, frame.data.as_ref(), frame.index))
+                .collect::<Vec<_>>(),
+            [
+                (LogStream::Stdout, &b"a"[..], 0),
+                (LogStream::Stderr, &b"b"[..], 1),
+                (LogStream::Stdout, &b"c"[..], 2),
+            ]
+        );
+        assert!(frames[0].received <= frames[2].received);
+    }
+
+    #[test]
+    fn lines_across_chunks() {
+        let mut decoder = LineDecoder::new();
+        assert!(decoder.decode(stdout(b"hello, ")).is_empty());
+        assert_eq!(
+            decoder.decode(stdout(b"world!\nfoo\r\nba")),
+            [
+                line(LogStream::Stdout, "hello, world!"),
+                line(LogStream::Stdout, "foo"),
+            ]
+        );
+        assert_eq!(decoder.finish(), [line(LogStream::Stdout, "ba")]);
+        assert!(decoder.finish().is_empty());
+    }
+
+    #[test]
+    fn split_multibyte_characters() {
+        let bytes = "héllo\n".as_bytes();
+        let mut decoder = LineDecoder::new();
+        assert!(decoder.decode(stdout(&bytes[..2])).is_empty());
+        assert_eq!(
+            decoder.decode(stdout(&bytes[2..])),
+            [line(LogStream::Stdout, "héllo")]
+        );
+    }
+
+    #[test]
+    fn separate_streams() {
+        let mut decoder = LineDecoder::new();
+        assert!(decoder.decode(stdout(b"out")).is_empty());
+        assert_eq!(
+            decoder.decode(stderr(b"err\n")),
+            [line(LogStream::Stderr, "err")]
+        );
+        assert_eq!(
+            decoder.decode(stdout(b"put\n")),
+            [line(LogStream::Stdout, "output")]
+        );
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut decoder = LineDecoder::new();
+        assert_eq!(
+            decoder.decode(stderr(b"\xFFok\n")),
+            [line(LogStream::Stderr, "\u{FFFD}ok")]
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_of_lines() {
+        let stream = tokio_stream::iter([Ok(stdout(b"a\nb")), Ok(stderr(b"c"))]);
+        let lines = LineDecoder::lines(stream)
+            .collect::<Result<Vec<_>>>()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            lines,
+            [
+                line(LogStream::Stdout, "a"),
+                line(LogStream::Stdout, "b"),
+                line(LogStream::Stderr, "c"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Networks.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use bollard::Docker;
+use bollard::query_parameters::ListContainersOptions;
+use bollard::query_parameters::ListNetworksOptions;
+use bollard::secret::ContainerSummary;
+use bollard::secret::Network;
+use tracing::debug;
+
+use crate::Error;
+use crate::Result;
+
+/// The names of the networks that the Docker daemon creates itself and that
+/// cannot be removed.
+const PREDEFINED_NETWORKS: &[&str] = &["bridge", "host", "none"];
+
+/// A summary of a network, as returned by
+/// [`Docker::list_networks()`](crate::Docker::list_networks).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NetworkSummary {
+    /// The ID of the network.
+    pub id: String,
+
+    /// The name of the network.
+    pub name: String,
+
+    /// The driver of the network (e.g., `bridge`).
+    pub driver: Option<String>,
+
+    /// The labels of the network.
+    pub labels: HashMap<String, String>,
+
+    /// Whether or not the network is one of the networks the Docker daemon
+    /// creates itself (`bridge`, `host`, and `none`), which cannot be removed.
+    pub predefined: bool,
+
+    /// Whether or not any container, running or not, is connected to the
+    /// network.
+    pub in_use: bool,
+}
+
+impl NetworkSummary {
+    /// Creates a summary of a network given the IDs of the networks that are
+    /// in use.
+    fn new(network: Network, in_use: &HashSet<String>) -> Self {
+        let id = network.id.unwrap_or_default();
+        let name = network.name.unwrap_or_default();
+
+        Self {
+            in_use: in_use.contains(&id),
+            predefined: PREDEFINED_NETWORKS.contains(&name.as_str()),
+            id,
+            name,
+            driver: network.driver,
+            labels: network.labels.unwrap_or_default(),
+        }
+    }
+}
+
+/// Gets the IDs of the networks that containers are connected to.
+fn networks_in_use(containers: &[ContainerSummary]) -> HashSet<String> {
+    containers
+        .iter()
+        .filter_map(|container| container.network_settings.as_ref()?.networks.as_ref())
+        .flat_map(|networks| networks.values())
+        .filter_map(|endpoint| endpoint.network_id.clone())
+        .collect()
+}
+
+/// Lists the networks matching the given filters (e.g., a `label` filter of
+/// `app=web`).
+///
+/// Whether each network is in use is determined from all containers, running
+/// or not.
+pub(crate) async fn list_networks(
+    docker: &Docker,
+    filters: HashMap<String, Vec<String>>,
+) -> Result<Vec<NetworkSummary>> {
+    debug!("listing networks");
+
+    let networks = docker
+        .list_networks(Some(ListNetworksOptions {
+            filters: Some(filters),
+        }))
+        .await
+        .map_err(Error::from)?;
+
+    // The networks listed do not include the containers connected to them, so
+    // they are gathered from the containers instead
+    let containers = docker
+        .list_containers(Some(ListContainersOptions {
+            all: true,
+            ..Default::default()
+        }))
+        .await
+        .map_err(Error::from)?;
+    let in_use = networks_in_use(&containers);
+
+    debug!("found {} networks", networks.len());
+
+    Ok(networks
+        .into_iter()
+        .map(|network| NetworkSummary::new(network, &in_use))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use bollard::secret::ContainerSummaryNetworkSettings;
+    use bollard::secret::EndpointSettings;
+
+    use super::*;
+
+    #[test]
+    fn summaries() {
+        let containers = [
+            ContainerSummary {
+                network_settings: Some(ContainerSummaryNetworkSettings {
+                    networks: Some(HashMap::from([(
+                        String::from("frontend"),
+                        EndpointSettings {
+                            network_id: Some(String::from("1")),
+                            ..Default::default()
+                        },
+                    )])),
+                }),
+                ..Default::default()
+            },
+            ContainerSummary::default(),
+        ];
+        let in_use = networks_in_use(&containers);
+
+        let network = |id: &str, name: &str| Network {
+            id: Some(id.to_string()),
+            name: Some(name.to_string()),
+            ..Default::default()
+        };
+
+        let summary = NetworkSummary::new(network("1", "frontend"), &in_use);
+        assert!(summary.in_use);
+        assert!(!summary.predefined);
+
+        let summary = NetworkSummary::new(network("2", "bridge"), &in_use);
+        assert!(!summary.in_use);
+        assert!(summary.predefined);
+    }
+}
@@ -0,0 +1,254 @@
+//! Raw requests to endpoints of the Docker daemon that [`bollard`] does not
+//! expose.
+
+#[cfg(unix)]
+use std::path::PathBuf;
+
+use bytes::Bytes;
+use http_body_util::BodyExt as _;
+use http_body_util::Full;
+use hyper::Method;
+use hyper::Request;
+use hyper::header;
+use hyper_util::rt::TokioIo;
+use serde::Deserialize;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tracing::debug;
+use tracing::trace;
+
+use crate::Error;
+use crate::Result;
+
+/// The address of a Docker daemon to send raw requests to.
+///
+/// Only unencrypted connections are supported: a daemon reached over TLS, SSH,
+/// or a Windows named pipe has no address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum DaemonAddress {
+    /// The path of a Unix socket.
+    #[cfg(unix)]
+    Unix(PathBuf),
+
+    /// A TCP address as `host:port`.
+    Tcp(String),
+}
+
+impl DaemonAddress {
+    /// Gets the address of a Docker daemon from a `DOCKER_HOST` URL (e.g.,
+    /// `unix:///var/run/docker.sock` or `tcp://localhost:2375`).
+    ///
+    /// `tls` is whether the connection is verified with TLS, as it is when
+    /// `DOCKER_TLS_VERIFY` is set.
+    pub(crate) fn from_host(host: &str, tls: bool) -> Option<Self> {
+        #[cfg(unix)]
+        if let Some(path) = host.strip_prefix("unix://") {
+            return Some(Self::Unix(PathBuf::from(path)));
+        }
+
+        let address = host
+            .strip_prefix("tcp://")
+            .or_else(|| host.strip_prefix("http://"))
+            .filter(|_| !tls)?
+            .trim_end_matches('/');
+
+        (!address.is_empty()).then(|| Self::Tcp(address.to_string()))
+    }
+
+    /// Sends a request to the Docker daemon.
+    ///
+    /// `path` is the path of the endpoint including the API version and any
+    /// query (e.g., `/v1.49/containers/app/checkpoints`), and `body` is sent
+    /// as JSON. A response with a status code other than success is returned
+    /// as the error [`bollard`] would return for it.
+    pub(crate) async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<()> {
+        debug!("sending `{method} {path}` to the Docker daemon");
+
+        let body = match body {
+            Some(body) => Bytes::from(serde_json::to_vec(&body).map_err(json_error)?),
+            None => Bytes::new(),
+        };
+
+        let request = Request::builder()
+            .method(method)
+            .uri(path)
+            // The Docker daemon ignores the host, but HTTP/1.1 requires one
+            .header(header::HOST, "docker")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::new(body))
+            .map_err(|err| Error::from(bollard::errors::Error::HttpClientError { err }))?;
+
+        match self {
+            #[cfg(unix)]
+            Self::Unix(path) => {
+                send(UnixStream::connect(path).await.map_err(io_error)?, request).await
+            }
+            Self::Tcp(address) => {
+                send(
+                    TcpStream::connect(address).await.map_err(io_error)?,
+                    request,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// The body of an error response from the Docker daemon.
+#[derive(Deserialize)]
+struct ErrorResponse {
+    /// The message of the error.
+    message: String,
+}
+
+/// Sends a request over a new connection to the Docker daemon.
+async fn send(
+    stream: impl AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    request: Request<Full<Bytes>>,
+) -> Result<()> {
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(hyper_error)?;
+
+    // The connection is driven until the response has been read, after which
+    // dropping the sender closes it
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            trace!("connection to the Docker daemon failed: {e}");
+        }
+    });
+
+    let response = sender.send_request(request).await.map_err(hyper_error)?;
+    let status = response.status();
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .map_err(hyper_error)?
+        .to_bytes();
+
+    if status.is_success() {
+        return Ok(());
+    }
+
+    // Like `bollard`, fall back to the body itself if it is not the usual JSON
+    let message = serde_json::from_slice::<ErrorResponse>(&body)
+        .map(|response| response.message)
+        .unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned());
+
+    Err(Error::from(
+        bollard::errors::Error::DockerResponseServerError {
+            status_code: status.as_u16(),
+            message,
+        },
+    ))
+}
+
+/// Converts an I/O error of a raw request into an [`Error`].
+fn io_error(err: std::io::Error) -> Error {
+    Error::from(bollard::errors::Error::IOError { err })
+}
+
+/// Converts an HTTP error of a raw request into an [`Error`].
+fn hyper_error(err: hyper::Error) -> Error {
+    Error::from(bollard::errors::Error::HyperResponseError { err })
+}
+
+/// Converts an error serializing the body of a raw request into an [`Error`].
+fn json_error(err: serde_json::Error) -> Error {
+    Error::from(bollard::errors::Error::JsonSerdeError { err })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn daemon_addresses() {
+        #[cfg(unix)]
+        assert_eq!(
+            DaemonAddress::from_host("unix:///var/run/docker.sock", false),
+            Some(DaemonAddress::Unix(PathBuf::from("/var/run/docker.sock")))
+        );
+        assert_eq!(
+            DaemonAddress::from_host("tcp://localhost:2375", false),
+            Some(DaemonAddress::Tcp(String::from("localhost:2375")))
+        );
+        assert_eq!(
+            DaemonAddress::from_host("http://10.0.0.1:2375/", false),
+            Some(DaemonAddress::Tcp(String::from("10.0.0.1:2375")))
+        );
+        assert_eq!(DaemonAddress::from_host("tcp://localhost:2376", true), None);
+        assert_eq!(
+            DaemonAddress::from_host("https://localhost:2376", false),
+            None
+        );
+        assert_eq!(DaemonAddress::from_host("ssh://user@host", false), None);
+        assert_eq!(
+            DaemonAddress::from_host("npipe:////./pipe/docker_engine", false),
+            None
+        );
+        assert_eq!(DaemonAddress::from_host("tcp://", false), None);
+    }
+
+    /// Serves a single response over TCP, returning the address of the server
+    /// and the request it received.
+    async fn serve(response: &'static str) -> (DaemonAddress, tokio::task::JoinHandle<String>) {
+        use tokio::io::AsyncReadExt as _;
+        use tokio::io::AsyncWriteExt as _;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = DaemonAddress::Tcp(listener.local_addr().unwrap().to_string());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") && !request.ends_with(b"}") {
+                let mut buf = [0; 1024];
+                let read = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..read]);
+            }
+
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        (address, server)
+    }
+
+    #[tokio::test]
+    async fn sent_requests() {
+        let (address, server) = serve("HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n").await;
+        address
+            .send(
+                Method::POST,
+                "/v1.49/containers/app/checkpoints",
+                Some(serde_json::json!({ "CheckpointID": "cp" })),
+            )
+            .await
+            .unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1.49/containers/app/checkpoints HTTP/1.1\r\n"));
+        assert!(request.ends_with(r#"{"CheckpointID":"cp"}"#));
+
+        let (address, _) = serve(
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 37\r\n\r\n{\"message\":\"No such container: app\"}\n",
+        )
+        .await;
+        let e = address
+            .send(Method::POST, "/v1.49/containers/app/start", None)
+            .await
+            .unwrap_err();
+        assert!(matches!(e, Error::NotFound(_)));
+        assert_eq!(e.status_code(), Some(404));
+        assert!(e.to_string().ends_with(": No such container: app"));
+    }
+}
@@ -0,0 +1,559 @@
+//! Streams from the Docker daemon that reconnect when they are dropped.
+
+use std::pin::Pin;
+use std::time::Duration;
+
+use bollard::container::LogOutput;
+use bollard::query_parameters::EventsOptions;
+use bollard::query_parameters::LogsOptions;
+use bollard::secret::EventMessage;
+use bytes::Bytes;
+use futures::Stream;
+use rand::Rng as _;
+use tokio_stream::StreamExt as _;
+use tracing::debug;
+use tracing::warn;
+
+use crate::Error;
+use crate::Result;
+
+/// The default delay before the first reconnection attempt.
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
+/// The default maximum delay between reconnection attempts.
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Options for reconnecting a stream from the Docker daemon.
+///
+/// When a stream is dropped (for example, because the Docker daemon
+/// restarted), it is reconnected after a delay that doubles with each failed
+/// attempt, up to [`max_backoff()`](Self::max_backoff). Each delay is jittered
+/// so that many clients do not reconnect at once.
+#[derive(Clone, Debug)]
+pub struct ReconnectOptions {
+    /// The delay before the first reconnection attempt.
+    initial_backoff: Duration,
+
+    /// The maximum delay between reconnection attempts.
+    max_backoff: Duration,
+
+    /// Whether or not a reconnected stream resumes from the last item seen.
+    resume: bool,
+}
+
+impl Default for ReconnectOptions {
+    fn default() -> Self {
+        Self {
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            resume: true,
+        }
+    }
+}
+
+impl ReconnectOptions {
+    /// Creates new [`ReconnectOptions`].
+    ///
+    /// By default, the first reconnection is attempted after 250 milliseconds,
+    /// the delay is capped at 30 seconds, and reconnected streams resume from
+    /// the last item seen.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the delay before the first reconnection attempt.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the maximum delay between reconnection attempts.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Sets whether or not a reconnected stream resumes from the last item
+    /// seen.
+    ///
+    /// When resuming, the stream is reconnected from the timestamp of the last
+    /// item seen, and items at or before that timestamp are skipped, so items
+    /// are neither missed nor repeated across a reconnection. Otherwise, the
+    /// reconnected stream starts from the present and anything that happened
+    /// while disconnected is missed.
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+}
+
+/// A capped exponential backoff with jitter.
+#[derive(Debug)]
+pub(crate) struct Backoff {
+    /// The initial delay.
+    initial: Duration,
+
+    /// The maximum delay.
+    max: Duration,
+
+    /// The upper bound of the next delay.
+    next: Duration,
+}
+
+impl Backoff {
+    /// Creates a new [`Backoff`].
+    pub(crate) fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            next: initial.min(max),
+        }
+    }
+
+    /// Gets the next delay and doubles the delay after it.
+    ///
+    /// The delay is chosen uniformly between half of the current bound and
+    /// the bound itself.
+    pub(crate) fn delay(&mut self) -> Duration {
+        let bound = self.next;
+        self.next = (self.next * 2).min(self.max);
+
+        let half = bound / 2;
+        let jitter = rand::rng().random_range(0..=(bound - half).as_nanos() as u64);
+        half + Duration::from_nanos(jitter)
+    }
+
+    /// Resets the delay to the initial delay.
+    pub(crate) fn reset(&mut self) {
+        self.next = self.initial.min(self.max);
+    }
+}
+
+/// Gets whether or not an error will recur if the stream is reconnected.
+///
+/// The Docker daemon rejects invalid requests (such as unknown filters or a
+/// missing container) with a client error; those are not retried.
+fn is_permanent(e: &Error) -> bool {
+    e.status_code()
+        .is_some_and(|code| (400..500).contains(&code))
+}
+
+/// A boxed stream from the Docker daemon.
+type BoxStream<T> =
+    Pin<Box<dyn Stream<Item = std::result::Result<T, bollard::errors::Error>> + Send>>;
+
+/// Streams events from the Docker daemon, reconnecting when the stream is
+/// dropped.
+pub(crate) fn events(
+    client: bollard::Docker,
+    options: EventsOptions,
+    reconnect: ReconnectOptions,
+) -> impl Stream<Item = Result<EventMessage>> + use<> {
+    /// The state of the stream.
+    struct State {
+        client: bollard::Docker,
+        options: EventsOptions,
+        reconnect: ReconnectOptions,
+        backoff: Backoff,
+        stream: Option<BoxStream<EventMessage>>,
+        seen: SeenEvents,
+        done: bool,
+    }
+
+    let backoff = Backoff::new(reconnect.initial_backoff, reconnect.max_backoff);
+    let state = State {
+        client,
+        options,
+        reconnect,
+        backoff,
+        stream: None,
+        seen: SeenEvents::default(),
+        done: false,
+    };
+
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if state.done {
+                return None;
+            }
+
+            let stream = match &mut state.stream {
+                Some(stream) => stream,
+                None => {
+                    let mut options = state.options.clone();
+                    if let Some(last) = state.seen.last.filter(|_| state.reconnect.resume) {
+                        options.since = Some(format_nanos(last));
+                        state.seen.resubscribed();
+                    }
+
+                    state
+                        .stream
+                        .insert(Box::pin(state.client.events(Some(options))))
+                }
+            };
+
+            let reason = match stream.next().await {
+                Some(Ok(event)) => {
+                    state.backoff.reset();
+
+                    if !state.seen.record(event.time_nano) {
+                        continue;
+                    }
+
+                    return Some((Ok(event), state));
+                }
+                Some(Err(e)) => {
+                    let e = Error::from(e);
+                    if is_permanent(&e) {
+                        state.done = true;
+                        return Some((Err(e), state));
+                    }
+
+                    e.to_string()
+                }
+                // The stream only ends on its own if it was given an end time
+                None if state.options.until.is_some() => return None,
+                None => String::from("the stream ended"),
+            };
+
+            state.stream = None;
+            let delay = state.backoff.delay();
+            warn!("event stream was dropped ({reason}); reconnecting in {delay:?}");
+            tokio::time::sleep(delay).await;
+        }
+    })
+}
+
+/// Follows the logs of a container, reconnecting when the stream is dropped.
+///
+/// The stream ends once the container has exited and all of its logs have
+/// been received.
+pub(crate) fn logs(
+    client: bollard::Docker,
+    name: String,
+    timestamps: bool,
+    reconnect: ReconnectOptions,
+) -> impl Stream<Item = Result<LogOutput>> + use<> {
+    /// The state of the stream.
+    struct State {
+        client: bollard::Docker,
+        name: String,
+        timestamps: bool,
+        reconnect: ReconnectOptions,
+        backoff: Backoff,
+        stream: Option<BoxStream<LogOutput>>,
+        last: Option<(i64, u32)>,
+        done: bool,
+    }
+
+    let backoff = Backoff::new(reconnect.initial_backoff, reconnect.max_backoff);
+    let state = State {
+        client,
+        name,
+        timestamps,
+        reconnect,
+        backoff,
+        stream: None,
+        last: None,
+        done: false,
+    };
+
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if state.done {
+                return None;
+            }
+
+            let stream = match &mut state.stream {
+                Some(stream) => stream,
+                None => {
+                    debug!("following logs of container `{name}`", name = state.name);
+
+                    let since = state
+                        .last
+                        .filter(|_| state.reconnect.resume)
+                        .map(|(secs, _)| i32::try_from(secs).unwrap_or(i32::MAX))
+                        .unwrap_or_default();
+
+                    // Timestamps are needed to know where to resume from
+                    let options = LogsOptions {
+                        follow: true,
+                        stdout: true,
+                        stderr: true,
+                        since,
+                        timestamps: state.timestamps || state.reconnect.resume,
+                        ..Default::default()
+                    };
+
+                    state
+                        .stream
+                        .insert(Box::pin(state.client.logs(&state.name, Some(options))))
+                }
+            };
+
+            let e = match stream.next().await {
+                Some(Ok(output)) => {
+                    state.backoff.reset();
+
+                    if !state.reconnect.resume {
+                        return Some((Ok(output), state));
+                    }
+
+                    match skip_seen(output, &mut state.last, state.timestamps) {
+                        Some(output) => return Some((Ok(output), state)),
+                        None => continue,
+                    }
+                }
+                Some(Err(e)) => Error::from(e),
+                None => return None,
+            };
+
+            if is_permanent(&e) {
+                state.done = true;
+                return Some((Err(e), state));
+            }
+
+            state.stream = None;
+            let delay = state.backoff.delay();
+            warn!(
+                "log stream of container `{name}` was dropped ({e}); reconnecting in {delay:?}",
+                name = state.name
+            );
+            tokio::time::sleep(delay).await;
+        }
+    })
+}
+
+/// The events delivered by an event stream, used to skip the events that the
+/// Docker daemon replays after the stream is resubscribed from the time of the
+/// last event.
+///
+/// Distinct events may share a time, so only as many events at the time of the
+/// last event as were delivered are skipped, and only while replaying.
+#[derive(Debug, Default)]
+struct SeenEvents {
+    /// The time of the last event delivered, in nanoseconds.
+    last: Option<i64>,
+
+    /// The number of events delivered at the time of the last event.
+    at_last: usize,
+
+    /// The number of events at the time of the last event still to be skipped,
+    /// if the stream is replaying events after a resubscription.
+    replaying: Option<usize>,
+}
+
+impl SeenEvents {
+    /// Notes that the stream was resubscribed from the time of the last event.
+    fn resubscribed(&mut self) {
+        self.replaying = Some(self.at_last);
+    }
+
+    /// Records an event with the given time, returning whether it is new.
+    fn record(&mut self, time: Option<i64>) -> bool {
+        if let (Some(skip), Some(time), Some(last)) = (&mut self.replaying, time, self.last) {
+            if time < last {
+                return false;
+            }
+
+            if time == last && *skip > 0 {
+                *skip -= 1;
+                return false;
+            }
+        }
+
+        self.replaying = None;
+        match time {
+            Some(time) if Some(time) == self.last => self.at_last += 1,
+            Some(time) => {
+                self.last = Some(time);
+                self.at_last = 1;
+            }
+            None => {}
+        }
+
+        true
+    }
+}
+
+/// Formats a Unix timestamp in nanoseconds as Docker expects for `since`.
+pub(crate) fn format_nanos(nanos: i64) -> String {
+    format!(
+        "{secs}.{nanos:09}",
+        secs = nanos.div_euclid(1_000_000_000),
+        nanos = nanos.rem_euclid(1_000_000_000)
+    )
+}
+
+/// Removes the lines of a chunk of log output at or before the last timestamp
+/// seen, updating the last timestamp.
+///
+/// Each line of the output is expected to be prefixed with its timestamp; the
+/// prefix is removed unless `keep_timestamps` is set. Returns `None` if no
+/// lines remain.
+fn skip_seen(
+    output: LogOutput,
+    last: &mut Option<(i64, u32)>,
+    keep_timestamps: bool,
+) -> Option<LogOutput> {
+    let (message, wrap): (_, fn(Bytes) -> LogOutput) = match output {
+        LogOutput::StdOut { message } => (message, |message| LogOutput::StdOut { message }),
+        LogOutput::StdErr { message } => (message, |message| LogOutput::StdErr { message }),
+        LogOutput::Console { message } => (message, |message| LogOutput::Console { message }),
+        LogOutput::StdIn { message } => (message, |message| LogOutput::StdIn { message }),
+    };
+
+    let mut kept = Vec::with_capacity(message.len());
+    for line in message.split_inclusive(|b| *b == b'\n') {
+        let parsed = line.iter().position(|b| *b == b' ').and_then(|end| {
+            Some((
+                parse_timestamp(std::str::from_utf8(&line[..end]).ok()?)?,
+                end,
+            ))
+        });
+
+        match parsed {
+            Some((timestamp, end)) => {
+                if last.is_some_and(|last| timestamp <= last) {
+                    continue;
+                }
+
+                *last = Some(timestamp);
+                if keep_timestamps {
+                    kept.extend_from_slice(line);
+                } else {
+                    kept.extend_from_slice(&line[end + 1..]);
+                }
+            }
+            // Continuations of a line split across chunks have no timestamp
+            None => kept.extend_from_slice(line),
+        }
+    }
+
+    (!kept.is_empty()).then(|| wrap(Bytes::from(kept)))
+}
+
+/// Parses an RFC 3339 timestamp in UTC (e.g.,
+/// `2025-01-02T03:04:05.123456789Z`) into seconds and nanoseconds since the
+/// Unix epoch.
+pub(crate) fn parse_timestamp(timestamp: &str) -> Option<(i64, u32)> {
+    let timestamp = timestamp.strip_suffix('Z')?;
+    let (date, time) = timestamp.split_once('T')?;
+
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time = time.splitn(3, ':').map(str::parse::<i64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+        || fraction.len() > 9
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<u32>().ok()? * 10u32.pow(9 - fraction.len() as u32)
+    };
+
+    // Days since the epoch of a date in the proleptic Gregorian calendar
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    Some((days * 86_400 + hour * 3_600 + minute * 60 + second, nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(350));
+
+        for bound in [100, 200, 350, 350] {
+            let delay = backoff.delay();
+            assert!(delay >= Duration::from_millis(bound / 2));
+            assert!(delay <= Duration::from_millis(bound));
+        }
+
+        backoff.reset();
+        assert!(backoff.delay() <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some((0, 0)));
+        assert_eq!(
+            parse_timestamp("2025-01-02T03:04:05.5Z"),
+            Some((1_735_787_045, 500_000_000))
+        );
+        assert_eq!(
+            parse_timestamp("2024-02-29T23:59:59.000000001Z"),
+            Some((1_709_251_199, 1))
+        );
+        assert_eq!(parse_timestamp("2025-01-02 03:04:05Z"), None);
+        assert_eq!(parse_timestamp("2025-13-02T03:04:05Z"), None);
+
+        assert_eq!(format_nanos(1_500_000_000), "1.500000000");
+    }
+
+    #[test]
+    fn skip_seen_events() {
+        let mut seen = SeenEvents::default();
+
+        // Distinct events sharing a time are all delivered
+        assert!(seen.record(Some(1)));
+        assert!(seen.record(Some(2)));
+        assert!(seen.record(Some(2)));
+        assert!(seen.record(None));
+
+        // Events replayed after a resubscription are skipped, but not a new
+        // event that shares the time of the last one
+        seen.resubscribed();
+        assert!(!seen.record(Some(1)));
+        assert!(!seen.record(Some(2)));
+        assert!(!seen.record(Some(2)));
+        assert!(seen.record(Some(2)));
+        assert!(seen.record(Some(3)));
+        assert!(seen.record(Some(3)));
+    }
+
+    #[test]
+    fn skip_seen_lines() {
+        let chunk = |message: &'static str| LogOutput::StdOut {
+            message: Bytes::from_static(message.as_bytes()),
+        };
+        let mut last = None;
+
+        let output = skip_seen(
+            chunk("1970-01-01T00:00:01Z a\n1970-01-01T00:00:02.5Z b\n"),
+            &mut last,
+            false,
+        );
+        assert_eq!(output.unwrap().into_bytes(), "a\nb\n");
+        assert_eq!(last, Some((2, 500_000_000)));
+
+        // Lines repeated after a reconnection are skipped
+        let output = skip_seen(
+            chunk("1970-01-01T00:00:02.5Z b\n1970-01-01T00:00:03Z c\n"),
+            &mut last,
+            true,
+        );
+        assert_eq!(output.unwrap().into_bytes(), "1970-01-01T00:00:03Z c\n");
+
+        assert!(skip_seen(chunk("1970-01-01T00:00:03Z c\n"), &mut last, false).is_none());
+    }
+}
@@ -11,9 +11,9 @@ use std::time::Duration;
 
 use bollard::Docker;
 use bollard::container::LogOutput;
-use bollard::query_parameters::{InspectContainerOptions, ListTasksOptions};
 use bollard::query_parameters::LogsOptions;
 use bollard::query_parameters::WaitContainerOptions;
+use bollard::query_parameters::{InspectContainerOptions, ListTasksOptions};
 use bollard::secret::ContainerWaitResponse;
 use bollard::secret::TaskState;
 
@@ -83,7 +83,7 @@ impl Service {
                     )])),
                 }))
                 .await
-                .map_err(Error::Docker)?;
+                .map_err(Error::from)?;
 
             if tasks.is_empty() {
                 // A task hasn't been created for the service yet, query again after a delay
@@ -163,7 +163,7 @@ impl Service {
                             .client
                             .inspect_container(&container_id, None::<InspectContainerOptions>)
                             .await
-                            .map_err(Error::Docker)?;
+                            .map_err(Error::from)?;
 
                         exit_code = Some(
                             container
@@ -256,7 +256,7 @@ impl Service {
                 },
             )
             .await
-            .map_err(Error::Docker)?;
+            .map_err(Error::from)?;
 
         #[cfg(unix)]
         let output = Output {
@@ -282,7 +282,7 @@ impl Service {
         self.client
             .delete_service(&self.id)
             .await
-            .map_err(Error::Docker)?;
+            .map_err(Error::from)?;
 
         Ok(())
     }
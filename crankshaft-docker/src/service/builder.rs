@@ -184,7 +184,7 @@ impl Builder {
                 None,
             )
             .await
-            .map_err(Error::Docker)?;
+            .map_err(Error::from)?;
 
         for warning in response.warnings.unwrap_or_default() {
             warn!("Docker daemon: {warning}");
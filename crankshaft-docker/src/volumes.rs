@@ -0,0 +1,136 @@
+//! Volumes.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use bollard::Docker;
+use bollard::query_parameters::ListContainersOptions;
+use bollard::query_parameters::ListVolumesOptions;
+use bollard::secret::ContainerSummary;
+use bollard::secret::MountPointTypeEnum;
+use bollard::secret::Volume;
+use tracing::debug;
+use tracing::warn;
+
+use crate::Error;
+use crate::Result;
+
+/// A summary of a volume, as returned by
+/// [`Docker::list_volumes()`](crate::Docker::list_volumes).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VolumeSummary {
+    /// The name of the volume.
+    pub name: String,
+
+    /// The driver of the volume (e.g., `local`).
+    pub driver: String,
+
+    /// The path of the volume on the host.
+    pub mountpoint: String,
+
+    /// The labels of the volume.
+    pub labels: HashMap<String, String>,
+
+    /// Whether or not the volume is mounted into any container, running or
+    /// not.
+    pub in_use: bool,
+}
+
+impl VolumeSummary {
+    /// Creates a summary of a volume given the names of the volumes that are
+    /// in use.
+    fn new(volume: Volume, in_use: &HashSet<String>) -> Self {
+        Self {
+            in_use: in_use.contains(&volume.name),
+            name: volume.name,
+            driver: volume.driver,
+            mountpoint: volume.mountpoint,
+            labels: volume.labels,
+        }
+    }
+}
+
+/// Gets the names of the volumes that are mounted into containers.
+fn volumes_in_use(containers: &[ContainerSummary]) -> HashSet<String> {
+    containers
+        .iter()
+        .flat_map(|container| container.mounts.iter().flatten())
+        .filter(|mount| mount.typ == Some(MountPointTypeEnum::VOLUME))
+        .filter_map(|mount| mount.name.clone())
+        .collect()
+}
+
+/// Lists the volumes matching the given filters (e.g., a `label` filter of
+/// `app=web`).
+///
+/// Whether each volume is in use is determined from all containers, running
+/// or not.
+pub(crate) async fn list_volumes(
+    docker: &Docker,
+    filters: HashMap<String, Vec<String>>,
+) -> Result<Vec<VolumeSummary>> {
+    debug!("listing volumes");
+
+    let response = docker
+        .list_volumes(Some(ListVolumesOptions {
+            filters: Some(filters),
+        }))
+        .await
+        .map_err(Error::from)?;
+
+    for warning in response.warnings.iter().flatten() {
+        warn!("{warning}");
+    }
+
+    let containers = docker
+        .list_containers(Some(ListContainersOptions {
+            all: true,
+            ..Default::default()
+        }))
+        .await
+        .map_err(Error::from)?;
+    let in_use = volumes_in_use(&containers);
+
+    let volumes = response.volumes.unwrap_or_default();
+    debug!("found {} volumes", volumes.len());
+
+    Ok(volumes
+        .into_iter()
+        .map(|volume| VolumeSummary::new(volume, &in_use))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use bollard::secret::MountPoint;
+
+    use super::*;
+
+    #[test]
+    fn summaries() {
+        let containers = [ContainerSummary {
+            mounts: Some(vec![
+                MountPoint {
+                    typ: Some(MountPointTypeEnum::VOLUME),
+                    name: Some(String::from("data")),
+                    ..Default::default()
+                },
+                MountPoint {
+                    typ: Some(MountPointTypeEnum::BIND),
+                    name: Some(String::from("cache")),
+                    ..Default::default()
+                },
+            ]),
+            ..Default::default()
+        }];
+        let in_use = volumes_in_use(&containers);
+
+        let volume = |name: &str| Volume {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        assert!(VolumeSummary::new(volume("data"), &in_use).in_use);
+        assert!(!VolumeSummary::new(volume("cache"), &in_use).in_use);
+    }
+}
@@ -540,7 +540,7 @@ fn add_shared_mounts<'a>(
 ) -> Result<()> {
     for target in iter {
         // Create new temporary directory in the provided temporary directory
-        // The call to `into_path` will prevent the directory from being deleted on
+        // The call to `keep` will prevent the directory from being deleted on
         // drop; instead, we're relying on the parent temporary directory to delete it
         let path = TempDir::new_in(tempdir)
             .wrap_err_with(|| {
@@ -549,7 +549,7 @@ fn add_shared_mounts<'a>(
                     tempdir = tempdir.display()
                 )
             })?
-            .into_path()
+            .keep()
             .into_os_string()
             .into_string()
             .map_err(|path| {